@@ -1,46 +1,60 @@
 use anyhow::{bail, format_err, Result};
-use cargo_new_release::CommandExt;
-use dialoguer::Confirm;
-use regex::Regex;
+use cargo_new_release::prompt::{self, InputMode, PromptPolicy};
+use cargo_new_release::{git, rust, CommandExt, GitHubRepo};
 use std::collections::HashMap;
 use std::env;
 use std::path::Path;
 use std::process::exit;
 use std::process::Command;
 
-fn fetch(rust_repo: &Path) -> Result<()> {
-    Command::git("fetch upstream")
+fn fetch(rust_repo: &Path, remote: &str) -> Result<()> {
+    Command::git_args(["fetch", remote])
         .current_dir(rust_repo)
         .run_success()?;
     Ok(())
 }
 
 /// Determine which PRs need to be milestoned.
-fn determine_milestones(auth: &str, rust_repo: &Path) -> Result<HashMap<String, Vec<u32>>> {
-    let log = Command::git("log --remotes=upstream -n 5 --format=%H src/tools/cargo")
-        .current_dir(rust_repo)
-        .run_stdout()?;
-    let subproject_re = Regex::new("Subproject commit ([0-9a-f]+)").unwrap();
+fn determine_milestones(
+    auth: &str,
+    repo: &GitHubRepo,
+    rust_repo: &Path,
+    remote: &str,
+) -> Result<HashMap<String, Vec<u32>>> {
+    let log = Command::git_args([
+        "log",
+        &format!("--remotes={}", remote),
+        "-n",
+        "5",
+        "--format=%H",
+        rust::CARGO_PATH,
+    ])
+    .current_dir(rust_repo)
+    .run_stdout()?;
+    let rust_git = git::open(rust_repo)?;
     let mut to_milestone = HashMap::new();
     for hash in log.lines() {
         eprintln!("checking {hash}");
-        let diff = Command::git(&format!("show -p {hash} src/tools/cargo"))
-            .current_dir(rust_repo)
-            .run_stdout()?;
-        let mut caps = subproject_re.captures_iter(&diff);
-        let cargo_start_hash = &caps.next().unwrap()[1];
-        let cargo_end_hash = &caps.next().unwrap()[1];
-        assert!(caps.next().is_none());
-        let version = version_at(rust_repo, hash)?;
-        let log = Command::git(&format!(
-            "log --first-parent {cargo_start_hash}...{cargo_end_hash}"
-        ))
-        .current_dir(rust_repo.join("src/tools/cargo"))
-        .run_stdout()?;
-        let commits = cargo_new_release::commits_in_log(&log);
-        assert!(!commits.is_empty());
-        for (pr_num, _, _) in commits {
-            if let Some((_milestone_number, milestone_title)) = current_milestone(auth, pr_num)? {
+        let cargo_start_hash = rust::cargo_commit_at(&*rust_git, &format!("{hash}^"))?;
+        let cargo_end_hash = rust::cargo_commit_at(&*rust_git, hash)?;
+        let version = rust::version_at(&*rust_git, hash)?.to_string();
+        let log = git::open(&rust_repo.join(rust::CARGO_PATH))?
+            .first_parent_log(&cargo_start_hash, &cargo_end_hash)?;
+        let commits = cargo_new_release::pull_requests(&log, repo);
+        if commits.is_empty() {
+            // Such as when cargo is moved back to revert an update.
+            eprintln!(
+                "warning: no new PRs between cargo {} and {} in {hash}, skipping",
+                &cargo_start_hash[..8],
+                &cargo_end_hash[..8]
+            );
+            continue;
+        }
+        for pr in commits {
+            let pr_num = pr.number;
+            if let Some((_milestone_number, milestone_title)) =
+                current_milestone(auth, repo, pr_num)?
+            {
                 if milestone_title == version {
                     eprintln!("skipping PR {pr_num}, already milestoned to {version}");
                 } else {
@@ -55,19 +69,16 @@ fn determine_milestones(auth: &str, rust_repo: &Path) -> Result<HashMap<String,
     Ok(to_milestone)
 }
 
-/// Determines the release version at the given git hash.
-fn version_at(rust_repo: &Path, hash: &str) -> Result<String> {
-    Command::git(&format!("show {hash}:src/version"))
-        .current_dir(rust_repo)
-        .run_stdout()
-}
-
 /// Returns the current milestone for the given PR.
 ///
 /// Returns None if no milestone currently set.
 /// Otherwise returns a tuple `(milestone_number, milestone_title)`.
-fn current_milestone(auth: &str, pr_num: u32) -> Result<Option<(String, String)>> {
-    let url = format!("https://api.github.com/repos/rust-lang/cargo/issues/{pr_num}");
+fn current_milestone(
+    auth: &str,
+    repo: &GitHubRepo,
+    pr_num: u32,
+) -> Result<Option<(String, String)>> {
+    let url = format!("{}/issues/{pr_num}", repo.api_url());
     let response = match ureq::get(&url)
         .set("Accept", "application/vnd.github.v3+json")
         .set("Authorization", &format!("Basic {auth}"))
@@ -100,31 +111,31 @@ fn current_milestone(auth: &str, pr_num: u32) -> Result<Option<(String, String)>
 }
 
 /// Confirm to start milestoning.
-fn confirm(milestones: &HashMap<String, Vec<u32>>) -> Result<()> {
+fn confirm(repo: &GitHubRepo, milestones: &HashMap<String, Vec<u32>>) -> Result<()> {
     eprintln!("milestoning:");
     for (version, prs) in milestones {
         eprintln!("{version}");
         for pr in prs {
-            eprintln!("    https://github.com/rust-lang/cargo/pull/{pr}");
+            eprintln!("    {}", repo.pr_url(*pr));
         }
     }
-    if !Confirm::new()
-        .with_prompt("Ready to milestone?")
-        .default(true)
-        .interact()?
-    {
+    if !prompt::confirm("milestone", "Ready to milestone?", true)? {
         exit(1);
     }
     Ok(())
 }
 
 /// Sets the milestone for the given PRs.
-fn set_milestones(auth: &str, milestones: &HashMap<String, Vec<u32>>) -> Result<()> {
+fn set_milestones(
+    auth: &str,
+    repo: &GitHubRepo,
+    milestones: &HashMap<String, Vec<u32>>,
+) -> Result<()> {
     for (version, prs) in milestones {
-        let milestone_num = get_milestone_num(auth, version)?;
+        let milestone_num = get_milestone_num(auth, repo, version)?;
         for pr in prs {
             eprintln!("updating pr {pr} to milestone {version} ({milestone_num})");
-            let url = format!("https://api.github.com/repos/rust-lang/cargo/issues/{pr}");
+            let url = format!("{}/issues/{pr}", repo.api_url());
             let response = ureq::patch(&url)
                 .set("Accept", "application/vnd.github.v3+json")
                 .set("Authorization", &format!("Basic {auth}"))
@@ -142,9 +153,9 @@ fn set_milestones(auth: &str, milestones: &HashMap<String, Vec<u32>>) -> Result<
 /// Returns the milestone number for the given release version.
 ///
 /// Creates the milestone if it doesn't already exist.
-fn get_milestone_num(auth: &str, version: &str) -> Result<i64> {
+fn get_milestone_num(auth: &str, repo: &GitHubRepo, version: &str) -> Result<i64> {
     // Create the milestone.
-    let url = format!("https://api.github.com/repos/rust-lang/cargo/milestones");
+    let url = format!("{}/milestones", repo.api_url());
     let number = match ureq::post(&url)
         .set("Accept", "application/vnd.github.v3+json")
         .set("Authorization", &format!("Basic {auth}"))
@@ -159,39 +170,95 @@ fn get_milestone_num(auth: &str, version: &str) -> Result<i64> {
             milestone_body["number"].as_i64().unwrap()
         }
         Err(ureq::Error::Status(422, _response)) => {
-            let milestones: serde_json::Value = ureq::get(&format!(
-                "https://api.github.com/repos/rust-lang/cargo/milestones?state=all&per_page=100"
-            ))
-            .set("Accept", "application/vnd.github.v3+json")
-            .set("Authorization", &format!("Basic {auth}"))
-            .call()?
-            .into_json()?;
-            milestones
-                .as_array()
-                .unwrap()
-                .into_iter()
-                .find(|milestone| milestone["title"] == version)
-                .map(|milestone| milestone["number"].as_i64().unwrap())
-                .ok_or_else(|| format_err!("could not find {version}"))?
+            // Already exists, search for it.
+            let mut url = format!("{}/milestones?state=all&per_page=100", repo.api_url());
+            loop {
+                let response = ureq::get(&url)
+                    .set("Accept", "application/vnd.github.v3+json")
+                    .set("Authorization", &format!("Basic {auth}"))
+                    .call()?;
+                let next = response.header("Link").and_then(next_page);
+                let milestones: serde_json::Value = response.into_json()?;
+                let found = milestones
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .find(|milestone| milestone["title"] == version)
+                    .map(|milestone| milestone["number"].as_i64().unwrap());
+                match (found, next) {
+                    (Some(number), _) => break number,
+                    (None, Some(next)) => url = next,
+                    (None, None) => bail!("could not find {version}"),
+                }
+            }
         }
         Err(e) => return Err(e.into()),
     };
     Ok(number)
 }
 
+/// Returns the `rel="next"` URL from a GitHub `Link` pagination header.
+fn next_page(link: &str) -> Option<String> {
+    link.split(',').find_map(|part| {
+        let (url, rel) = part.split_once(';')?;
+        if rel.trim() != "rel=\"next\"" {
+            return None;
+        }
+        let url = url.trim().strip_prefix('<')?.strip_suffix('>')?;
+        Some(url.to_string())
+    })
+}
+
 fn doit() -> Result<()> {
-    let rust_repo = env::args()
-        .skip(1)
-        .next()
-        .ok_or_else(|| format_err!("expected path to rust repo as first argument"))?;
+    let mut rust_repo = None;
+    let mut repo = None;
+    let mut rust_remote = None;
+    let mut input_mode = InputMode::Interactive;
+    let mut prompt_policy = None;
+    let mut args = cargo_new_release::parse_verbosity(env::args().skip(1).collect()).into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--yes" => input_mode = InputMode::Yes,
+            "--no-input" => input_mode = InputMode::NoInput,
+            "--prompt-policy" => {
+                prompt_policy = Some(
+                    args.next()
+                        .ok_or_else(|| format_err!("--prompt-policy requires a path"))?,
+                );
+            }
+            "--repo" => {
+                repo = Some(
+                    args.next()
+                        .ok_or_else(|| format_err!("--repo requires `owner/name`"))?,
+                );
+            }
+            "--rust-remote" => {
+                rust_remote = Some(
+                    args.next()
+                        .ok_or_else(|| format_err!("--rust-remote requires a remote name"))?,
+                );
+            }
+            s if s.starts_with('-') => bail!("unknown option `{}`", s),
+            _ if rust_repo.is_none() => rust_repo = Some(arg),
+            _ => bail!("unexpected argument `{}`", arg),
+        }
+    }
+    prompt::set_input_mode(input_mode);
+    if let Some(path) = prompt_policy {
+        prompt::set_policy(PromptPolicy::load(Path::new(&path))?);
+    }
+    let rust_repo =
+        rust_repo.ok_or_else(|| format_err!("expected path to rust repo as first argument"))?;
+    let repo = GitHubRepo::from_config(repo.as_deref())?;
+    let rust_remote = rust::remote_from_config(rust_remote.as_deref())?;
     let token =
         env::var("GITHUB_TOKEN").expect("GITHUB_TOKEN must be set in <username>:<token> format");
     let auth = base64::encode(token);
     let rust_repo = Path::new(&rust_repo);
-    fetch(&rust_repo)?;
-    let milestones = determine_milestones(&auth, &rust_repo)?;
-    confirm(&milestones)?;
-    set_milestones(&auth, &milestones)?;
+    fetch(rust_repo, &rust_remote)?;
+    let milestones = determine_milestones(&auth, &repo, rust_repo, &rust_remote)?;
+    confirm(&repo, &milestones)?;
+    set_milestones(&auth, &repo, &milestones)?;
     Ok(())
 }
 
@@ -201,5 +268,6 @@ fn main() {
         for cause in e.chain().skip(1) {
             eprintln!("caused by: {}", cause);
         }
+        exit(1);
     }
 }
@@ -0,0 +1,119 @@
+//! Choosing the next version of the package being released.
+
+use anyhow::{bail, format_err, Result};
+use semver::Version;
+use std::fmt;
+
+/// How to change the version, as given to `--bump`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Bump {
+    Major,
+    Minor,
+    Patch,
+    /// An exact version, such as a prerelease.
+    Exact(Version),
+}
+
+impl Bump {
+    /// Parses `major`, `minor`, `patch`, or a version.
+    pub fn parse(s: &str) -> Result<Bump> {
+        match s {
+            "major" => Ok(Bump::Major),
+            "minor" => Ok(Bump::Minor),
+            "patch" => Ok(Bump::Patch),
+            _ => Version::parse(s).map(Bump::Exact).map_err(|e| {
+                format_err!(
+                    "expected `major`, `minor`, `patch`, or a version, got `{}`: {}",
+                    s,
+                    e
+                )
+            }),
+        }
+    }
+
+    /// Returns whether this only changes the patch version, as for a fix
+    /// released from a release branch.
+    pub fn is_patch(&self) -> bool {
+        match self {
+            Bump::Patch => true,
+            Bump::Exact(version) => version.patch > 0,
+            Bump::Major | Bump::Minor => false,
+        }
+    }
+
+    /// Returns the version after `current`.
+    ///
+    /// A prerelease bumps to its own release when that is the same kind of
+    /// bump, so `0.72.0-beta.1` with `minor` gives `0.72.0`.
+    pub fn apply(&self, current: &Version) -> Version {
+        let pre = current.is_prerelease();
+        let Version {
+            major,
+            minor,
+            patch,
+            ..
+        } = *current;
+        match self {
+            Bump::Major if pre && minor == 0 && patch == 0 => Version::new(major, 0, 0),
+            Bump::Major => Version::new(major + 1, 0, 0),
+            Bump::Minor if pre && patch == 0 => Version::new(major, minor, 0),
+            Bump::Minor => Version::new(major, minor + 1, 0),
+            Bump::Patch if pre => Version::new(major, minor, patch),
+            Bump::Patch => Version::new(major, minor, patch + 1),
+            Bump::Exact(version) => version.clone(),
+        }
+    }
+}
+
+impl fmt::Display for Bump {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Bump::Major => f.write_str("major"),
+            Bump::Minor => f.write_str("minor"),
+            Bump::Patch => f.write_str("patch"),
+            Bump::Exact(version) => write!(f, "{}", version),
+        }
+    }
+}
+
+/// Checks that `next` can be released after `current`, given the existing
+/// git tags.
+///
+/// Tags that aren't versions, optionally prefixed with `v`, are ignored.
+pub fn validate(current: &Version, next: &Version, tags: &[String]) -> Result<()> {
+    if next <= current {
+        bail!("{} is not newer than the current version {}", next, current);
+    }
+    for tag in tags {
+        let version = match tag_version(tag) {
+            Some(version) => version,
+            None => continue,
+        };
+        if version == *next {
+            bail!("{} has already been released, tag `{}` exists", next, tag);
+        }
+        if (version.major, version.minor) == (next.major, next.minor) && version > *next {
+            bail!(
+                "{} is older than {}, which has already been released as tag `{}`",
+                next,
+                version,
+                tag
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Returns the tag of the newest release before `current`, if any.
+pub fn previous_tag<'a>(current: &Version, tags: &'a [String]) -> Option<&'a str> {
+    tags.iter()
+        .filter_map(|tag| Some((tag_version(tag)?, tag)))
+        .filter(|(version, _)| version < current && !version.is_prerelease())
+        .max_by(|a, b| a.0.cmp(&b.0))
+        .map(|(_, tag)| tag.as_str())
+}
+
+/// Returns the version a tag such as `0.70.0` or `v0.70.0` is for.
+fn tag_version(tag: &str) -> Option<Version> {
+    Version::parse(tag.strip_prefix('v').unwrap_or(tag)).ok()
+}
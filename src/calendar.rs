@@ -0,0 +1,204 @@
+//! The Rust release schedule.
+//!
+//! A new version is released every six weeks, counting from 1.0.0. On the
+//! day of a release the beta becomes stable and nightly branches to become
+//! the new beta, so nightly is always two versions ahead of stable.
+//!
+//! Releases that didn't happen on schedule are listed in a file of
+//! exceptions. Each line is `1.N = YYYY-MM-DD`, and blank lines and lines
+//! starting with `#` are ignored.
+
+use crate::CommandExt;
+use anyhow::{bail, format_err, Context, Result};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use time::Date;
+
+/// The date format used in exception files and output.
+pub const DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// The day the schedule counts from. 1.0.0 is an exception, as it was
+/// released a day later.
+const SCHEDULE_START: Date = time::date!(2015 - 05 - 14);
+
+/// The release dates of Rust versions.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Calendar {
+    /// Actual release dates by minor version, where they differ from the
+    /// schedule.
+    exceptions: BTreeMap<u64, Date>,
+}
+
+/// The minor versions of each channel on some day.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Channels {
+    pub stable: u64,
+    pub beta: u64,
+    pub nightly: u64,
+}
+
+impl Default for Calendar {
+    fn default() -> Calendar {
+        Calendar::new()
+    }
+}
+
+impl Calendar {
+    /// Returns the calendar without any exceptions from a file.
+    pub fn new() -> Calendar {
+        let mut exceptions = BTreeMap::new();
+        // 1.0.0 was released on a Friday, every release since on a Thursday.
+        exceptions.insert(0, time::date!(2015 - 05 - 15));
+        Calendar { exceptions }
+    }
+
+    /// Loads a file of exceptions.
+    pub fn load(path: &Path) -> Result<Calendar> {
+        let contents = fs::read_to_string(path).with_context(|| {
+            format_err!("failed to read schedule exceptions {}", path.display())
+        })?;
+        Calendar::parse(&contents)
+            .with_context(|| format_err!("invalid schedule exceptions {}", path.display()))
+    }
+
+    /// Parses the contents of a file of exceptions.
+    pub fn parse(contents: &str) -> Result<Calendar> {
+        let mut calendar = Calendar::new();
+        for (i, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (version, date) = line
+                .split_once('=')
+                .ok_or_else(|| format_err!("line {}: expected `1.N = YYYY-MM-DD`", i + 1))?;
+            let minor = parse_version(version.trim())
+                .ok_or_else(|| format_err!("line {}: expected a version like 1.70", i + 1))?;
+            let date = parse_date(date.trim()).with_context(|| format_err!("line {}", i + 1))?;
+            calendar.exceptions.insert(minor, date);
+        }
+        for (&minor, &date) in &calendar.exceptions {
+            let previous_after = minor.checked_sub(1).is_some_and(|previous| {
+                !calendar
+                    .release_date(previous)
+                    .is_ok_and(|previous| previous < date)
+            });
+            if previous_after || calendar.released_by(minor.saturating_add(1), date) {
+                bail!(
+                    "1.{} on {} is out of order with the releases around it",
+                    minor,
+                    date.format(DATE_FORMAT)
+                );
+            }
+        }
+        Ok(calendar)
+    }
+
+    /// Loads the exceptions to use.
+    ///
+    /// This is the file given by the `--schedule-exceptions` flag if any,
+    /// otherwise the `cargo-new-release.schedule-exceptions` git config
+    /// setting. Without either there are no exceptions.
+    pub fn from_config(flag: Option<&str>) -> Result<Calendar> {
+        let path = match flag {
+            Some(flag) => flag.to_string(),
+            None => {
+                Command::git("config --default= --get --path cargo-new-release.schedule-exceptions")
+                    .run_stdout()?
+            }
+        };
+        if path.is_empty() {
+            return Ok(Calendar::new());
+        }
+        Calendar::load(Path::new(&path))
+    }
+
+    /// Returns the release date of Rust `1.<minor>.0`.
+    ///
+    /// Fails for versions scheduled after the year 9999.
+    pub fn release_date(&self, minor: u64) -> Result<Date> {
+        if let Some(date) = self.exceptions.get(&minor) {
+            return Ok(*date);
+        }
+        let day = i64::try_from(minor)
+            .ok()
+            .and_then(|minor| minor.checked_mul(42))
+            .and_then(|days| days.checked_add(SCHEDULE_START.julian_day()))
+            .filter(|&day| day <= time::date!(9999 - 12 - 31).julian_day())
+            .ok_or_else(|| format_err!("1.{} is too far in the future to schedule", minor))?;
+        Ok(Date::from_julian_day(day))
+    }
+
+    /// Returns whether `1.<minor>.0` is released on or before `date`.
+    fn released_by(&self, minor: u64, date: Date) -> bool {
+        self.release_date(minor)
+            .is_ok_and(|release| release <= date)
+    }
+
+    /// Returns the newest version released on or before `date`, or `None`
+    /// before 1.0.0.
+    pub fn stable_on(&self, date: Date) -> Option<u64> {
+        if !self.released_by(0, date) {
+            return None;
+        }
+        // Start from the schedule, then allow for exceptions moving a
+        // release either side of `date`.
+        let mut minor = ((date.julian_day() - SCHEDULE_START.julian_day()).max(0) / 42) as u64;
+        while minor > 0 && !self.released_by(minor, date) {
+            minor -= 1;
+        }
+        while self.released_by(minor + 1, date) {
+            minor += 1;
+        }
+        Some(minor)
+    }
+
+    /// Returns the versions on each channel on `date`, or `None` before
+    /// 1.0.0.
+    pub fn channels_on(&self, date: Date) -> Option<Channels> {
+        let stable = self.stable_on(date)?;
+        Some(Channels {
+            stable,
+            beta: stable + 1,
+            nightly: stable + 2,
+        })
+    }
+
+    /// Returns the next time nightly branches to beta after `date`, as the
+    /// version that becomes beta and the date.
+    pub fn next_branch_cut(&self, date: Date) -> Result<(u64, Date)> {
+        let beta = self.stable_on(date).map_or(0, |stable| stable + 1);
+        Ok((beta + 1, self.release_date(beta)?))
+    }
+
+    /// Returns the number of days from `date` until the next branch cut.
+    pub fn days_until_branch_cut(&self, date: Date) -> Result<i64> {
+        let (_, cut) = self.next_branch_cut(date)?;
+        Ok(cut.julian_day() - date.julian_day())
+    }
+}
+
+/// Parses a version like `1.70` or `1.70.0`, returning the minor version.
+pub fn parse_version(version: &str) -> Option<u64> {
+    let mut parts = version.split('.');
+    if parts.next() != Some("1") {
+        return None;
+    }
+    let minor = parts.next()?.parse().ok()?;
+    match (parts.next(), parts.next()) {
+        (None, _) | (Some("0"), None) => Some(minor),
+        _ => None,
+    }
+}
+
+/// Parses a `YYYY-MM-DD` date.
+pub fn parse_date(date: &str) -> Result<Date> {
+    Date::parse(date, DATE_FORMAT).map_err(|e| format_err!("invalid date `{}`: {}", date, e))
+}
+
+/// Returns today's date in UTC.
+pub fn today() -> Date {
+    time::OffsetDateTime::now_utc().date()
+}
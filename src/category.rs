@@ -0,0 +1,75 @@
+//! Guessing which changelog section a PR belongs in.
+//!
+//! GitHub labels are checked first, since they are set by people who know
+//! the PR: any `Z-*` label (an unstable `-Z` flag) means "Nightly only",
+//! `C-bug` means "Fixed", and `C-enhancement` or `C-feature-*` means
+//! "Added". Otherwise the PR title is used: mentions of nightly, unstable,
+//! or a `-Z` flag mean "Nightly only", and the leading verb decides between
+//! "Added", "Changed", and "Fixed".
+//!
+//! PRs that match none of these are left for a human to sort.
+
+use crate::PullRequest;
+
+/// The changelog sections a PR can be sorted into.
+pub const CATEGORIES: [&str; 4] = ["Added", "Changed", "Fixed", "Nightly only"];
+
+const ADDED: &str = CATEGORIES[0];
+const CHANGED: &str = CATEGORIES[1];
+const FIXED: &str = CATEGORIES[2];
+const NIGHTLY: &str = CATEGORIES[3];
+
+/// Returns the changelog section for a PR, or `None` if it can't be
+/// determined.
+pub fn categorize(pr: &PullRequest, labels: &[String]) -> Option<&'static str> {
+    by_labels(labels).or_else(|| by_title(&pr.title))
+}
+
+fn by_labels(labels: &[String]) -> Option<&'static str> {
+    if labels.iter().any(|l| l.starts_with("Z-")) {
+        Some(NIGHTLY)
+    } else if labels.iter().any(|l| l == "C-bug") {
+        Some(FIXED)
+    } else if labels
+        .iter()
+        .any(|l| l == "C-enhancement" || l.starts_with("C-feature-"))
+    {
+        Some(ADDED)
+    } else {
+        None
+    }
+}
+
+fn by_title(title: &str) -> Option<&'static str> {
+    let lower = title.to_lowercase();
+    // A conventional-commit style prefix such as `fix(resolver):` may say
+    // what kind of change it is, otherwise look past it.
+    let (kind, rest) = match lower.split_once(':') {
+        Some((prefix, rest)) if !prefix.contains(' ') => {
+            (prefix.split('(').next().unwrap_or_default(), rest)
+        }
+        _ => ("", lower.as_str()),
+    };
+    let verb = rest.split_whitespace().next().unwrap_or_default();
+    // Stabilizing an unstable feature is an addition, even though the title
+    // usually names the `-Z` flag.
+    if matches!(verb, "stabilize" | "stabilise") {
+        return Some(ADDED);
+    }
+    let is_nightly = lower
+        .split(|c: char| !c.is_alphanumeric() && c != '-')
+        .any(|word| matches!(word, "nightly" | "unstable") || word.starts_with("-z"));
+    if is_nightly {
+        return Some(NIGHTLY);
+    }
+    match (kind, verb) {
+        ("fix", _) | (_, "fix" | "fixes" | "fixed" | "correct") => Some(FIXED),
+        ("feat", _) | (_, "add" | "adds" | "added" | "implement" | "support" | "introduce") => {
+            Some(ADDED)
+        }
+        (_, "change" | "changes" | "improve" | "make" | "rename" | "remove" | "deprecate") => {
+            Some(CHANGED)
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,431 @@
+//! Parsing and editing cargo's `CHANGELOG.md`.
+//!
+//! The changelog is a list of releases, newest first:
+//!
+//! ```text
+//! ## Cargo 1.70 (2023-06-01)
+//! [9880b408...HEAD](https://github.com/rust-lang/cargo/compare/9880b408...HEAD)
+//!
+//! ### Added
+//!
+//! - Added `cargo foo`.
+//!   [#1234](https://github.com/rust-lang/cargo/pull/1234)
+//! ```
+//!
+//! Parsing never fails. Anything that doesn't fit this layout is kept as
+//! plain text, and every node keeps the text it was parsed from, so an
+//! unmodified [`Changelog`] displays as exactly the original file.
+
+use regex::Regex;
+use std::fmt;
+use std::sync::OnceLock;
+
+pub mod coverage;
+pub mod lint;
+
+/// A parsed changelog.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Changelog {
+    /// Everything before the first release, such as the `# Changelog` title.
+    pub preamble: String,
+    /// The releases, newest first.
+    pub releases: Vec<Release>,
+}
+
+/// A `## ` heading and everything up to the next one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Release {
+    heading: String,
+    version: Option<String>,
+    date: Option<String>,
+    /// The line number of the heading, or 0 if not parsed from a file.
+    pub line: usize,
+    /// The compare link on the line after the heading.
+    pub compare: Option<CompareLink>,
+    /// Content before the first section.
+    pub intro: Vec<Block>,
+    pub sections: Vec<Section>,
+}
+
+/// A link such as `[abc...HEAD](https://github.com/o/r/compare/abc...HEAD)`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CompareLink {
+    text: String,
+    start: String,
+    end: String,
+    url: String,
+    /// The line number of the link, or 0 if not parsed from a file.
+    pub line: usize,
+}
+
+/// A `### ` heading and everything up to the next heading.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Section {
+    heading: String,
+    /// The line number of the heading, or 0 if not parsed from a file.
+    pub line: usize,
+    pub blocks: Vec<Block>,
+}
+
+/// A piece of a release or section.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Block {
+    /// A list item.
+    Entry(Entry),
+    /// Any other line, such as a blank line, kept verbatim.
+    Text(String),
+}
+
+/// A `- ` list item, including its indented continuation lines.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Entry {
+    text: String,
+    prs: Vec<PrLink>,
+    /// The line number of the first line, or 0 if not parsed from a file.
+    pub line: usize,
+}
+
+/// A `[#N](url)` link in an entry.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PrLink {
+    pub number: u32,
+    pub url: String,
+    /// The line number of the link, or 0 if not parsed from a file.
+    pub line: usize,
+}
+
+fn regex(cell: &'static OnceLock<Regex>, re: &str) -> &'static Regex {
+    cell.get_or_init(|| Regex::new(re).unwrap())
+}
+
+impl Changelog {
+    /// Parses the contents of a changelog.
+    pub fn parse(contents: &str) -> Changelog {
+        let mut changelog = Changelog {
+            preamble: String::new(),
+            releases: Vec::new(),
+        };
+        let mut lines = contents.split_inclusive('\n').enumerate().peekable();
+        while let Some((i, line)) = lines.next() {
+            let line_no = i + 1;
+            if line.starts_with("## ") {
+                let mut release = Release::from_heading(line, line_no);
+                if let Some((i, next)) = lines.peek() {
+                    release.compare = CompareLink::parse(next, i + 1);
+                    if release.compare.is_some() {
+                        lines.next();
+                    }
+                }
+                changelog.releases.push(release);
+                continue;
+            }
+            let release = match changelog.releases.last_mut() {
+                Some(release) => release,
+                None => {
+                    changelog.preamble.push_str(line);
+                    continue;
+                }
+            };
+            if line.starts_with("### ") {
+                release.sections.push(Section {
+                    heading: line.to_string(),
+                    line: line_no,
+                    blocks: Vec::new(),
+                });
+                continue;
+            }
+            let blocks = match release.sections.last_mut() {
+                Some(section) => &mut section.blocks,
+                None => &mut release.intro,
+            };
+            if line.starts_with("- ") || line.trim_end() == "-" {
+                let mut text = line.to_string();
+                while let Some((_, next)) = lines.peek() {
+                    if next.starts_with(char::is_whitespace) && !next.trim().is_empty() {
+                        text.push_str(next);
+                        lines.next();
+                    } else {
+                        break;
+                    }
+                }
+                blocks.push(Block::Entry(Entry::from_text(text, line_no)));
+            } else {
+                blocks.push(Block::Text(line.to_string()));
+            }
+        }
+        changelog
+    }
+
+    /// Returns every entry, with the release it is in.
+    pub fn entries(&self) -> impl Iterator<Item = (&Release, &Entry)> {
+        self.releases
+            .iter()
+            .flat_map(|release| release.entries().map(move |entry| (release, entry)))
+    }
+
+    /// Returns whether any entry links to the given PR.
+    pub fn contains_pr(&self, number: u32) -> bool {
+        self.entries()
+            .any(|(_, entry)| entry.prs().iter().any(|pr| pr.number == number))
+    }
+}
+
+impl fmt::Display for Changelog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.preamble)?;
+        for release in &self.releases {
+            write!(f, "{}", release)?;
+        }
+        Ok(())
+    }
+}
+
+impl Release {
+    /// Creates a release with a compare link and an empty section for each
+    /// of `sections`.
+    pub fn new(version: &str, date: &str, compare: CompareLink, sections: &[&str]) -> Release {
+        let mut release = Release::from_heading(&format!("## Cargo {} ({})\n", version, date), 0);
+        release.compare = Some(compare);
+        release.intro.push(Block::Text("\n".to_string()));
+        release.sections = sections
+            .iter()
+            .map(|title| Section {
+                heading: format!("### {}\n", title),
+                line: 0,
+                blocks: vec![Block::Text("\n".to_string())],
+            })
+            .collect();
+        release
+    }
+
+    fn from_heading(heading: &str, line: usize) -> Release {
+        static RE: OnceLock<Regex> = OnceLock::new();
+        let re = regex(
+            &RE,
+            r"^## Cargo ([0-9]+\.[0-9]+(?:\.[0-9]+)?)(?: \(([^)]*)\))?",
+        );
+        let caps = re.captures(heading);
+        Release {
+            heading: heading.to_string(),
+            version: caps.as_ref().map(|caps| caps[1].to_string()),
+            date: caps.and_then(|caps| caps.get(2).map(|m| m.as_str().to_string())),
+            line,
+            compare: None,
+            intro: Vec::new(),
+            sections: Vec::new(),
+        }
+    }
+
+    /// The heading text after `## `.
+    pub fn title(&self) -> &str {
+        self.heading[3..].trim_end()
+    }
+
+    /// The version, such as `1.70` or `1.66.1`, if the heading is
+    /// `## Cargo <version>`.
+    pub fn version(&self) -> Option<&str> {
+        self.version.as_deref()
+    }
+
+    /// The minor part of [`Release::version`].
+    pub fn minor(&self) -> Option<u64> {
+        self.version()?.split('.').nth(1)?.parse().ok()
+    }
+
+    /// The patch part of [`Release::version`], 0 if there isn't one.
+    pub fn patch(&self) -> Option<u64> {
+        match self.version()?.split('.').nth(2) {
+            Some(patch) => patch.parse().ok(),
+            None => Some(0),
+        }
+    }
+
+    /// The date in parentheses after the version, if any.
+    pub fn date(&self) -> Option<&str> {
+        self.date.as_deref()
+    }
+
+    /// Returns whether this is the release of `version`, such as `1.70` or
+    /// `1.70.0`.
+    pub fn is_version(&self, version: &str) -> bool {
+        let mut parts = version.split('.').skip(1);
+        let minor = parts.next().and_then(|minor| minor.parse().ok());
+        let patch = parts.next().map_or(Some(0), |patch| patch.parse().ok());
+        minor.is_some() && patch.is_some() && (self.minor(), self.patch()) == (minor, patch)
+    }
+
+    /// Returns the section with the given title.
+    pub fn section(&self, title: &str) -> Option<&Section> {
+        self.sections
+            .iter()
+            .find(|section| section.title() == title)
+    }
+
+    /// Returns the section with the given title.
+    pub fn section_mut(&mut self, title: &str) -> Option<&mut Section> {
+        self.sections
+            .iter_mut()
+            .find(|section| section.title() == title)
+    }
+
+    /// Adds an entry to the list before the first section.
+    pub fn push_intro(&mut self, entry: Entry) {
+        push_entry(&mut self.intro, entry);
+    }
+
+    /// Returns every entry in the release.
+    pub fn entries(&self) -> impl Iterator<Item = &Entry> {
+        std::iter::once(&self.intro)
+            .chain(self.sections.iter().map(|section| &section.blocks))
+            .flat_map(|blocks| entries(blocks))
+    }
+}
+
+impl fmt::Display for Release {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.heading)?;
+        if let Some(compare) = &self.compare {
+            f.write_str(&compare.text)?;
+        }
+        write_blocks(f, &self.intro)?;
+        for section in &self.sections {
+            f.write_str(&section.heading)?;
+            write_blocks(f, &section.blocks)?;
+        }
+        Ok(())
+    }
+}
+
+impl CompareLink {
+    /// Creates a link comparing `start...end` in the given GitHub repo URL.
+    pub fn new(repo_url: &str, start: &str, end: &str) -> CompareLink {
+        let url = format!("{}/compare/{}...{}", repo_url, start, end);
+        CompareLink {
+            text: format!("[{}...{}]({})\n", start, end, url),
+            start: start.to_string(),
+            end: end.to_string(),
+            url,
+            line: 0,
+        }
+    }
+
+    fn parse(text: &str, line: usize) -> Option<CompareLink> {
+        static RE: OnceLock<Regex> = OnceLock::new();
+        let re = regex(&RE, r"^\[([^\].]+)\.\.\.([^\]]+)\]\(([^)]*)\)\s*$");
+        let caps = re.captures(text)?;
+        Some(CompareLink {
+            text: text.to_string(),
+            start: caps[1].to_string(),
+            end: caps[2].to_string(),
+            url: caps[3].to_string(),
+            line,
+        })
+    }
+
+    /// The start of the range in the link text.
+    pub fn start(&self) -> &str {
+        &self.start
+    }
+
+    /// The end of the range in the link text, such as `HEAD`.
+    pub fn end(&self) -> &str {
+        &self.end
+    }
+
+    /// The URL the link points to.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Changes the end of the range, in both the text and the URL.
+    pub fn set_end(&mut self, end: &str) {
+        if let Some(base) = self.url.strip_suffix(&format!("...{}", self.end)) {
+            self.url = format!("{}...{}", base, end);
+        }
+        self.end = end.to_string();
+        let newline = if self.text.ends_with('\n') { "\n" } else { "" };
+        self.text = format!("[{}...{}]({}){}", self.start, self.end, self.url, newline);
+    }
+}
+
+impl Section {
+    /// The heading text after `### `.
+    pub fn title(&self) -> &str {
+        self.heading[4..].trim_end()
+    }
+
+    /// Adds an entry after the existing ones.
+    pub fn push(&mut self, entry: Entry) {
+        push_entry(&mut self.blocks, entry);
+    }
+
+    /// Returns the entries in the section.
+    pub fn entries(&self) -> impl Iterator<Item = &Entry> {
+        entries(&self.blocks)
+    }
+}
+
+impl Entry {
+    /// Creates an entry for a PR.
+    pub fn new(title: &str, number: u32, url: &str) -> Entry {
+        Entry::from_text(format!("- {} \n  [#{}]({})\n", title, number, url), 0)
+    }
+
+    fn from_text(text: String, line: usize) -> Entry {
+        static RE: OnceLock<Regex> = OnceLock::new();
+        let re = regex(&RE, r"\[#([0-9]+)\]\(([^)]*)\)");
+        let prs = re
+            .captures_iter(&text)
+            .filter_map(|caps| {
+                let offset = text[..caps.get(0)?.start()].matches('\n').count();
+                Some(PrLink {
+                    number: caps[1].parse().ok()?,
+                    url: caps[2].to_string(),
+                    line: if line == 0 { 0 } else { line + offset },
+                })
+            })
+            .collect();
+        Entry { text, prs, line }
+    }
+
+    /// The text of the entry, including the leading `- `.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// The PRs linked from the entry.
+    pub fn prs(&self) -> &[PrLink] {
+        &self.prs
+    }
+}
+
+fn entries(blocks: &[Block]) -> impl Iterator<Item = &Entry> {
+    blocks.iter().filter_map(|block| match block {
+        Block::Entry(entry) => Some(entry),
+        Block::Text(_) => None,
+    })
+}
+
+/// Adds an entry after the last one in `blocks`, or after any leading
+/// blank lines if there are none, keeping a blank line after the list.
+fn push_entry(blocks: &mut Vec<Block>, entry: Entry) {
+    let is_blank = |block: &Block| matches!(block, Block::Text(text) if text.trim().is_empty());
+    let idx = match blocks.iter().rposition(|b| matches!(b, Block::Entry(_))) {
+        Some(last) => last + 1,
+        None => blocks.iter().take_while(|b| is_blank(b)).count(),
+    };
+    blocks.insert(idx, Block::Entry(entry));
+    if !blocks.get(idx + 1).is_some_and(is_blank) {
+        blocks.insert(idx + 1, Block::Text("\n".to_string()));
+    }
+}
+
+fn write_blocks(f: &mut fmt::Formatter<'_>, blocks: &[Block]) -> fmt::Result {
+    for block in blocks {
+        match block {
+            Block::Entry(entry) => f.write_str(&entry.text)?,
+            Block::Text(text) => f.write_str(text)?,
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,63 @@
+//! Comparing the changelog against the PRs merged for a release.
+
+use super::{Changelog, PrLink};
+use crate::PullRequest;
+
+/// How well a release's changelog covers the PRs merged for it.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Coverage {
+    /// PRs that aren't in the changelog at all.
+    pub missing: Vec<PullRequest>,
+    /// PRs documented only under other versions, with where they are.
+    pub wrong_version: Vec<(PullRequest, Vec<Location>)>,
+    /// Links in the release's changelog to PRs that weren't merged for it.
+    pub extra: Vec<PrLink>,
+}
+
+/// Where a PR is referenced in the changelog.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Location {
+    /// The title of the release, such as `Cargo 1.70 (2023-06-01)`.
+    pub release: String,
+    pub line: usize,
+}
+
+/// Compares the changelog for `version`, such as `1.70` or `1.66.1`,
+/// against the PRs merged for it.
+pub fn coverage(changelog: &Changelog, version: &str, prs: &[PullRequest]) -> Coverage {
+    let mut coverage = Coverage::default();
+    for pr in prs {
+        let mut found = false;
+        let mut elsewhere = Vec::new();
+        for (release, entry) in changelog.entries() {
+            for link in entry.prs().iter().filter(|link| link.number == pr.number) {
+                if release.is_version(version) {
+                    found = true;
+                } else {
+                    elsewhere.push(Location {
+                        release: release.title().to_string(),
+                        line: link.line,
+                    });
+                }
+            }
+        }
+        if found {
+            continue;
+        }
+        if elsewhere.is_empty() {
+            coverage.missing.push(pr.clone());
+        } else {
+            coverage.wrong_version.push((pr.clone(), elsewhere));
+        }
+    }
+    for release in changelog.releases.iter().filter(|r| r.is_version(version)) {
+        for entry in release.entries() {
+            for link in entry.prs() {
+                if !prs.iter().any(|pr| pr.number == link.number) {
+                    coverage.extra.push(link.clone());
+                }
+            }
+        }
+    }
+    coverage
+}
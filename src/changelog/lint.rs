@@ -0,0 +1,268 @@
+//! Checks for mistakes in the changelog.
+
+use super::{Changelog, CompareLink, Release};
+use crate::calendar::{self, Calendar};
+use crate::git::{self, GitBackend};
+use regex::Regex;
+use std::collections::HashMap;
+use std::fmt;
+
+/// How serious a [`Finding`] is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    /// Expected while a release is in progress, such as an empty section.
+    Warning,
+}
+
+/// A problem found in the changelog.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Finding {
+    pub line: usize,
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        write!(f, "{}: {}: {}", self.line, severity, self.message)
+    }
+}
+
+/// Checks the changelog, returning the problems found ordered by line.
+///
+/// Release dates are checked against `calendar`. With a repository,
+/// compare links whose ends are written differently, such as a hash and a
+/// `rust-1.N.0` branch, are checked to be the same point in history.
+pub fn lint(
+    changelog: &Changelog,
+    calendar: &Calendar,
+    repo: Option<&dyn GitBackend>,
+) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let mut error = |line, message| {
+        findings.push(Finding {
+            line,
+            severity: Severity::Error,
+            message,
+        })
+    };
+    check_links(changelog, &mut error);
+    check_versions(changelog, calendar, &mut error);
+    check_compare_links(changelog, repo, &mut error);
+
+    for release in &changelog.releases {
+        let name = name(release);
+        if release.compare.is_none() {
+            findings.push(Finding {
+                line: release.line,
+                severity: Severity::Warning,
+                message: format!("{} has no compare link", name),
+            });
+        }
+        for section in &release.sections {
+            if section.entries().next().is_none() {
+                findings.push(Finding {
+                    line: section.line,
+                    severity: Severity::Warning,
+                    message: format!("section `{}` of {} is empty", section.title(), name),
+                });
+            }
+        }
+    }
+    findings.sort_by_key(|finding| finding.line);
+    findings
+}
+
+/// Returns how to refer to a release in messages.
+fn name(release: &Release) -> String {
+    match release.version() {
+        Some(version) => version.to_string(),
+        None => format!("`{}`", release.title()),
+    }
+}
+
+/// Checks that PR links point to the PR they name, and that no PR is
+/// listed twice.
+fn check_links(changelog: &Changelog, error: &mut impl FnMut(usize, String)) {
+    let url_re = Regex::new(r"/(?:pull|issues)/([0-9]+)/?$").unwrap();
+    let mut seen = HashMap::new();
+    for (_, entry) in changelog.entries() {
+        for pr in entry.prs() {
+            let linked = url_re
+                .captures(&pr.url)
+                .and_then(|caps| caps[1].parse::<u32>().ok());
+            match linked {
+                Some(number) if number == pr.number => {}
+                Some(number) => error(
+                    pr.line,
+                    format!("link for #{} points to #{}: {}", pr.number, number, pr.url),
+                ),
+                None => error(
+                    pr.line,
+                    format!("link for #{} is not a PR URL: {}", pr.number, pr.url),
+                ),
+            }
+            match seen.get(&pr.number) {
+                Some(first) => error(
+                    pr.line,
+                    format!("#{} is already referenced on line {}", pr.number, first),
+                ),
+                None => {
+                    seen.insert(pr.number, pr.line);
+                }
+            }
+        }
+    }
+}
+
+/// Checks that versions are newest first, and dated on the release
+/// schedule.
+fn check_versions(
+    changelog: &Changelog,
+    calendar: &Calendar,
+    error: &mut impl FnMut(usize, String),
+) {
+    let mut newer: Option<(&Release, (u64, u64))> = None;
+    for release in &changelog.releases {
+        let (minor, patch) = match (release.minor(), release.patch()) {
+            (Some(minor), Some(patch)) => (minor, patch),
+            _ => continue,
+        };
+        if let Some((newer, key)) = newer {
+            if (minor, patch) >= key {
+                error(
+                    release.line,
+                    format!(
+                        "{} is listed after {} (line {}), versions should be newest first",
+                        name(release),
+                        name(newer),
+                        newer.line
+                    ),
+                );
+            }
+        }
+        newer = Some((release, (minor, patch)));
+        // Point releases are made whenever they are needed.
+        if patch != 0 {
+            continue;
+        }
+        let expected = match calendar.release_date(minor) {
+            Ok(date) => date.format(calendar::DATE_FORMAT),
+            Err(e) => {
+                error(release.line, e.to_string());
+                continue;
+            }
+        };
+        match release.date() {
+            Some(date) if date == expected => {}
+            Some(date) => error(
+                release.line,
+                format!(
+                    "{} is dated {}, but is scheduled for {}",
+                    name(release),
+                    date,
+                    expected
+                ),
+            ),
+            None => error(
+                release.line,
+                format!("{} has no date, expected {}", name(release), expected),
+            ),
+        }
+    }
+}
+
+/// Checks that compare links agree with their URL and version, and that
+/// each release starts where the previous one ended.
+fn check_compare_links(
+    changelog: &Changelog,
+    repo: Option<&dyn GitBackend>,
+    error: &mut impl FnMut(usize, String),
+) {
+    for (i, release) in changelog.releases.iter().enumerate() {
+        let compare = match &release.compare {
+            Some(compare) => compare,
+            None => continue,
+        };
+        let range = format!("{}...{}", compare.start(), compare.end());
+        if !compare.url().ends_with(&range) {
+            error(
+                compare.line,
+                format!(
+                    "compare link text `{}` does not match its URL: {}",
+                    range,
+                    compare.url()
+                ),
+            );
+        }
+        if compare.end() == "HEAD" {
+            if i != 0 {
+                error(
+                    compare.line,
+                    format!(
+                        "only the latest release can compare to HEAD, not {}",
+                        name(release)
+                    ),
+                );
+            }
+        } else if compare.end().starts_with("rust-") {
+            if let (Some(minor), Some(patch)) = (release.minor(), release.patch()) {
+                let expected = format!("rust-1.{}.{}", minor, patch);
+                if compare.end() != expected {
+                    error(
+                        compare.line,
+                        format!(
+                            "compare link of {} ends at `{}`, expected `{}`",
+                            name(release),
+                            compare.end(),
+                            expected
+                        ),
+                    );
+                }
+            }
+        }
+        let older = match changelog.releases.get(i + 1) {
+            Some(older) => older,
+            None => continue,
+        };
+        if let Some(older_compare) = &older.compare {
+            if !is_continuous(compare, older_compare, repo) {
+                error(
+                    compare.line,
+                    format!(
+                        "compare link of {} starts at `{}`, but {} (line {}) ends at `{}`",
+                        name(release),
+                        compare.start(),
+                        name(older),
+                        older_compare.line,
+                        older_compare.end()
+                    ),
+                );
+            }
+        }
+    }
+}
+
+/// Returns whether `newer` starts where `older` ends.
+///
+/// A release starts where the previous one branched, so the start may be an
+/// ancestor of a branch that later received backports.
+fn is_continuous(newer: &CompareLink, older: &CompareLink, repo: Option<&dyn GitBackend>) -> bool {
+    if newer.start() == older.end() {
+        return true;
+    }
+    let repo = match repo {
+        Some(repo) => repo,
+        None => return false,
+    };
+    let resolve = |rev: &str| git::resolve(repo, rev).ok();
+    match (resolve(newer.start()), resolve(older.end())) {
+        (Some(start), Some(end)) => repo.is_ancestor(&start, &end).unwrap_or(false),
+        _ => false,
+    }
+}
@@ -0,0 +1,254 @@
+//! Read-only access to git repositories.
+//!
+//! By default this shells out to the `git` command-line tool. When built
+//! with the `git2` feature, the repository is read in-process with libgit2
+//! instead, unless `CARGO_NEW_RELEASE_GIT=cli` is set.
+
+use crate::CommandExt;
+use anyhow::{bail, format_err, Result};
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+#[cfg(feature = "git2")]
+mod libgit2;
+
+#[cfg(feature = "git2")]
+pub use libgit2::Git2Backend;
+
+/// A commit read from the log.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Commit {
+    /// The full commit hash.
+    pub hash: String,
+    /// The name of the author.
+    pub author: String,
+    /// The full commit message.
+    pub message: String,
+}
+
+/// An entry in a git tree, as shown by `git ls-tree`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TreeEntry {
+    /// The file mode, such as `100644` or `160000`.
+    pub mode: String,
+    /// The object type, such as `blob`, `tree`, or `commit`.
+    pub kind: String,
+    /// The object hash.
+    pub hash: String,
+}
+
+/// Read-only operations on a git repository.
+pub trait GitBackend {
+    /// Resolves a revision to a full commit hash.
+    fn rev_parse(&self, rev: &str) -> Result<String>;
+
+    /// Returns whether the working tree has uncommitted changes to tracked
+    /// files.
+    fn is_dirty(&self) -> Result<bool>;
+
+    /// Returns the commits in `start..end` following only the first parent,
+    /// newest first.
+    ///
+    /// Commits only reachable from `start` are left out, so this is empty
+    /// when `end` is an ancestor of `start`.
+    fn first_parent_log(&self, start: &str, end: &str) -> Result<Vec<Commit>>;
+
+    /// Returns the tree entry for `path` at the given revision, or `None`
+    /// if the path does not exist.
+    fn tree_entry(&self, rev: &str, path: &str) -> Result<Option<TreeEntry>>;
+
+    /// Returns the contents of the file at `path` at the given revision.
+    fn show_file(&self, rev: &str, path: &str) -> Result<String>;
+
+    /// Returns the paths changed by a commit, compared to its first parent.
+    fn changed_files(&self, rev: &str) -> Result<Vec<String>>;
+
+    /// Returns the paths that differ between two revisions.
+    fn diff_files(&self, from: &str, to: &str) -> Result<Vec<String>>;
+
+    /// Returns the paths of all files at the given revision.
+    fn list_files(&self, rev: &str) -> Result<Vec<String>>;
+
+    /// Returns whether `ancestor` is reachable from `rev`, or is the same
+    /// commit.
+    fn is_ancestor(&self, ancestor: &str, rev: &str) -> Result<bool>;
+
+    /// Returns the names of all tags.
+    fn tags(&self) -> Result<Vec<String>>;
+}
+
+/// Resolves a revision to a full commit hash, also trying the branch of
+/// that name on the `upstream` remote.
+///
+/// Release branches such as `rust-1.70.0` usually only exist upstream.
+pub fn resolve(repo: &dyn GitBackend, rev: &str) -> Result<String> {
+    repo.rev_parse(rev)
+        .or_else(|_| repo.rev_parse(&format!("upstream/{}", rev)))
+        .map_err(|_| format_err!("could not find `{}` or `upstream/{}`", rev, rev))
+}
+
+/// Opens the repository at the given path with the default backend.
+pub fn open(path: &Path) -> Result<Box<dyn GitBackend>> {
+    #[cfg(feature = "git2")]
+    {
+        if std::env::var("CARGO_NEW_RELEASE_GIT").as_deref() != Ok("cli") {
+            return Ok(Box::new(libgit2::Git2Backend::open(path)?));
+        }
+    }
+    Ok(Box::new(CliBackend::new(path)))
+}
+
+/// A backend that runs the `git` command-line tool.
+pub struct CliBackend {
+    dir: PathBuf,
+}
+
+impl CliBackend {
+    pub fn new(dir: &Path) -> CliBackend {
+        CliBackend {
+            dir: dir.to_path_buf(),
+        }
+    }
+
+    fn git<I, S>(&self, args: I) -> Command
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        let mut cmd = Command::git_args(args);
+        cmd.current_dir(&self.dir);
+        cmd
+    }
+}
+
+impl GitBackend for CliBackend {
+    fn rev_parse(&self, rev: &str) -> Result<String> {
+        self.git([
+            "rev-parse",
+            "--verify",
+            "--quiet",
+            &format!("{}^{{commit}}", rev),
+        ])
+        .run_stdout()
+    }
+
+    fn is_dirty(&self) -> Result<bool> {
+        Ok(!self
+            .git(["diff-index", "--quiet", "HEAD", "."])
+            .run_success()?)
+    }
+
+    fn first_parent_log(&self, start: &str, end: &str) -> Result<Vec<Commit>> {
+        // `start..end` rather than `start...end`, so that this matches the
+        // libgit2 revwalk and a submodule moving backwards lists nothing
+        // instead of the commits it drops.
+        //
+        // An explicit format keeps the output independent of user settings
+        // like `format.pretty` or `log.showSignature`.
+        let log = self
+            .git([
+                "log",
+                "-z",
+                "--first-parent",
+                "--no-show-signature",
+                "--format=%H%n%an%n%B",
+                &format!("{}..{}", start, end),
+            ])
+            .run_stdout()?;
+        log.split('\0')
+            .filter(|entry| !entry.trim().is_empty())
+            .map(|entry| {
+                let mut parts = entry.trim_start().splitn(3, '\n');
+                let hash = parts.next().unwrap_or_default();
+                let author = parts
+                    .next()
+                    .ok_or_else(|| format_err!("unexpected log output for {}", hash))?;
+                Ok(Commit {
+                    hash: hash.to_string(),
+                    author: author.to_string(),
+                    message: parts.next().unwrap_or_default().to_string(),
+                })
+            })
+            .collect()
+    }
+
+    fn tree_entry(&self, rev: &str, path: &str) -> Result<Option<TreeEntry>> {
+        let line = self.git(["ls-tree", rev, "--", path]).run_stdout()?;
+        if line.is_empty() {
+            return Ok(None);
+        }
+        // Format is `<mode> SP <type> SP <hash> TAB <path>`.
+        let (info, entry_path) = line
+            .split_once('\t')
+            .ok_or_else(|| format_err!("unexpected ls-tree output: {}", line))?;
+        if entry_path != path {
+            bail!(
+                "ls-tree returned `{}` when looking for `{}`",
+                entry_path,
+                path
+            );
+        }
+        match info.split(' ').collect::<Vec<_>>()[..] {
+            [mode, kind, hash] => Ok(Some(TreeEntry {
+                mode: mode.to_string(),
+                kind: kind.to_string(),
+                hash: hash.to_string(),
+            })),
+            _ => bail!("unexpected ls-tree output: {}", line),
+        }
+    }
+
+    fn show_file(&self, rev: &str, path: &str) -> Result<String> {
+        // `run_stdout` trims the output, which would lose the final newline.
+        let output = self
+            .git(["cat-file", "blob", &format!("{}:{}", rev, path)])
+            .run_output()?;
+        Ok(output.stdout)
+    }
+
+    fn changed_files(&self, rev: &str) -> Result<Vec<String>> {
+        self.diff_files(&format!("{}^1", rev), rev)
+    }
+
+    fn diff_files(&self, from: &str, to: &str) -> Result<Vec<String>> {
+        let files = self
+            .git([
+                "diff-tree",
+                "-r",
+                "-z",
+                "--no-commit-id",
+                "--name-only",
+                from,
+                to,
+            ])
+            .run_stdout()?;
+        Ok(split_nul(&files))
+    }
+
+    fn list_files(&self, rev: &str) -> Result<Vec<String>> {
+        let files = self
+            .git(["ls-tree", "-r", "-z", "--name-only", rev])
+            .run_stdout()?;
+        Ok(split_nul(&files))
+    }
+
+    fn is_ancestor(&self, ancestor: &str, rev: &str) -> Result<bool> {
+        self.git(["merge-base", "--is-ancestor", ancestor, rev])
+            .run_success()
+    }
+
+    fn tags(&self) -> Result<Vec<String>> {
+        let tags = self.git(["tag", "--list"]).run_stdout()?;
+        Ok(tags.lines().map(String::from).collect())
+    }
+}
+
+/// Splits `-z` output into paths.
+fn split_nul(output: &str) -> Vec<String> {
+    output
+        .split('\0')
+        .filter(|file| !file.is_empty())
+        .map(String::from)
+        .collect()
+}
@@ -0,0 +1,137 @@
+//! In-process backend using libgit2.
+
+use super::{Commit, GitBackend, TreeEntry};
+use anyhow::{format_err, Context, Result};
+use git2::{ObjectType, Repository, Sort, Status, StatusOptions, TreeWalkMode, TreeWalkResult};
+use std::path::Path;
+
+pub struct Git2Backend {
+    repo: Repository,
+}
+
+impl Git2Backend {
+    pub fn open(path: &Path) -> Result<Git2Backend> {
+        let repo = Repository::discover(path)
+            .with_context(|| format_err!("failed to open git repository at {}", path.display()))?;
+        Ok(Git2Backend { repo })
+    }
+
+    fn commit(&self, rev: &str) -> Result<git2::Commit<'_>> {
+        self.repo
+            .revparse_single(rev)
+            .and_then(|obj| obj.peel_to_commit())
+            .with_context(|| format_err!("failed to resolve `{}`", rev))
+    }
+
+    fn diff_trees(&self, old: &git2::Tree<'_>, new: &git2::Tree<'_>) -> Result<Vec<String>> {
+        let diff = self.repo.diff_tree_to_tree(Some(old), Some(new), None)?;
+        Ok(diff
+            .deltas()
+            .filter_map(|delta| delta.new_file().path().or_else(|| delta.old_file().path()))
+            .map(|path| path.to_string_lossy().into_owned())
+            .collect())
+    }
+}
+
+impl GitBackend for Git2Backend {
+    fn rev_parse(&self, rev: &str) -> Result<String> {
+        Ok(self.commit(rev)?.id().to_string())
+    }
+
+    fn is_dirty(&self) -> Result<bool> {
+        let mut opts = StatusOptions::new();
+        opts.include_untracked(false).include_ignored(false);
+        let statuses = self.repo.statuses(Some(&mut opts))?;
+        Ok(statuses
+            .iter()
+            .any(|entry| entry.status() != Status::CURRENT))
+    }
+
+    fn first_parent_log(&self, start: &str, end: &str) -> Result<Vec<Commit>> {
+        let mut walk = self.repo.revwalk()?;
+        walk.set_sorting(Sort::TOPOLOGICAL)?;
+        walk.simplify_first_parent()?;
+        walk.push(self.commit(end)?.id())?;
+        walk.hide(self.commit(start)?.id())?;
+        walk.map(|id| {
+            let commit = self.repo.find_commit(id?)?;
+            let author = String::from_utf8_lossy(commit.author().name_bytes()).into_owned();
+            Ok(Commit {
+                hash: commit.id().to_string(),
+                author,
+                message: String::from_utf8_lossy(commit.message_bytes()).into_owned(),
+            })
+        })
+        .collect()
+    }
+
+    fn tree_entry(&self, rev: &str, path: &str) -> Result<Option<TreeEntry>> {
+        let tree = self.commit(rev)?.tree()?;
+        let entry = match tree.get_path(Path::new(path)) {
+            Ok(entry) => entry,
+            Err(e) if e.code() == git2::ErrorCode::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let kind = match entry.kind() {
+            Some(ObjectType::Blob) => "blob",
+            Some(ObjectType::Tree) => "tree",
+            Some(ObjectType::Commit) => "commit",
+            Some(ObjectType::Tag) => "tag",
+            _ => "unknown",
+        };
+        Ok(Some(TreeEntry {
+            mode: format!("{:06o}", entry.filemode()),
+            kind: kind.to_string(),
+            hash: entry.id().to_string(),
+        }))
+    }
+
+    fn show_file(&self, rev: &str, path: &str) -> Result<String> {
+        let tree = self.commit(rev)?.tree()?;
+        let blob = tree
+            .get_path(Path::new(path))
+            .and_then(|entry| entry.to_object(&self.repo))
+            .and_then(|obj| obj.peel_to_blob())
+            .with_context(|| format_err!("failed to read `{}:{}`", rev, path))?;
+        Ok(String::from_utf8_lossy(blob.content()).into_owned())
+    }
+
+    fn changed_files(&self, rev: &str) -> Result<Vec<String>> {
+        let commit = self.commit(rev)?;
+        let parent = commit
+            .parent(0)
+            .with_context(|| format_err!("`{}` has no parent", rev))?;
+        self.diff_trees(&parent.tree()?, &commit.tree()?)
+    }
+
+    fn diff_files(&self, from: &str, to: &str) -> Result<Vec<String>> {
+        self.diff_trees(&self.commit(from)?.tree()?, &self.commit(to)?.tree()?)
+    }
+
+    fn list_files(&self, rev: &str) -> Result<Vec<String>> {
+        let tree = self.commit(rev)?.tree()?;
+        let mut files = Vec::new();
+        tree.walk(TreeWalkMode::PreOrder, |dir, entry| {
+            if entry.kind() != Some(ObjectType::Tree) {
+                files.push(format!(
+                    "{}{}",
+                    dir,
+                    String::from_utf8_lossy(entry.name_bytes())
+                ));
+            }
+            TreeWalkResult::Ok
+        })?;
+        Ok(files)
+    }
+
+    fn is_ancestor(&self, ancestor: &str, rev: &str) -> Result<bool> {
+        let ancestor = self.commit(ancestor)?.id();
+        let rev = self.commit(rev)?.id();
+        Ok(ancestor == rev || self.repo.graph_descendant_of(rev, ancestor)?)
+    }
+
+    fn tags(&self) -> Result<Vec<String>> {
+        let tags = self.repo.tag_names(None)?;
+        Ok(tags.iter().flatten().map(String::from).collect())
+    }
+}
@@ -0,0 +1,55 @@
+//! A small client for the GitHub REST API.
+
+use crate::GitHubRepo;
+use anyhow::{bail, Result};
+use std::env;
+
+/// Authenticated access to the GitHub API.
+pub struct Client {
+    /// The base64-encoded `user:token`.
+    auth: String,
+}
+
+impl Client {
+    /// Creates a client from the `GITHUB_TOKEN` environment variable, in
+    /// `<username>:<token>` format.
+    ///
+    /// Returns `None` if it is not set.
+    pub fn from_env() -> Option<Client> {
+        let token = env::var("GITHUB_TOKEN").ok()?;
+        Some(Client {
+            auth: base64::encode(token),
+        })
+    }
+
+    /// Fetches the JSON at the given API URL.
+    pub fn get(&self, url: &str) -> Result<serde_json::Value> {
+        let response = match ureq::get(url)
+            .set("Accept", "application/vnd.github.v3+json")
+            .set("Authorization", &format!("Basic {}", self.auth))
+            .call()
+        {
+            Ok(response) => response,
+            Err(ureq::Error::Status(status, response)) => {
+                let body = response.into_string().unwrap_or_default();
+                bail!("{url} failed status {status}: {body}");
+            }
+            Err(e) => return Err(e.into()),
+        };
+        Ok(response.into_json()?)
+    }
+
+    /// Returns the names of the labels on the given issue or PR.
+    pub fn labels(&self, repo: &GitHubRepo, number: u32) -> Result<Vec<String>> {
+        let issue = self.get(&format!("{}/issues/{number}", repo.api_url()))?;
+        Ok(issue["labels"]
+            .as_array()
+            .map(|labels| {
+                labels
+                    .iter()
+                    .filter_map(|label| label["name"].as_str().map(String::from))
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+}
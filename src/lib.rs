@@ -1,38 +1,157 @@
-use anyhow::{bail, Result};
+use anyhow::{bail, format_err, Result};
 use regex::Regex;
-use std::process::{Command, Stdio};
+use std::env;
+use std::ffi::OsStr;
+use std::fmt;
+use std::process::{Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::time::Instant;
+
+pub mod bump;
+pub mod calendar;
+pub mod category;
+pub mod changelog;
+pub mod git;
+pub mod github;
+pub mod opener;
+pub mod prompt;
+pub mod review;
+pub mod rust;
+pub mod version_gates;
+pub mod workspace;
+
+static VERBOSITY: AtomicU8 = AtomicU8::new(0);
+
+/// The maximum number of bytes of stderr included in an error.
+pub const MAX_STDERR_LEN: usize = 4096;
+
+/// Sets how much detail to log about commands that are run.
+///
+/// At level 1 each command is logged with its working directory, duration,
+/// and exit status. At level 2 any captured output is logged as well.
+pub fn set_verbosity(level: u8) {
+    VERBOSITY.store(level, Ordering::Relaxed);
+}
+
+/// Sets the verbosity from the `-v`, `-vv`, and `--verbose` flags accepted
+/// by every command, returning the remaining arguments.
+///
+/// Each `v` and each `--verbose` raises the level by one.
+pub fn parse_verbosity(args: Vec<String>) -> Vec<String> {
+    let mut level = 0usize;
+    let rest = args
+        .into_iter()
+        .filter(|arg| {
+            let count = match arg.strip_prefix('-') {
+                Some("-verbose") => 1,
+                Some(vs) if !vs.is_empty() && vs.bytes().all(|b| b == b'v') => vs.len(),
+                _ => return true,
+            };
+            level += count;
+            false
+        })
+        .collect();
+    set_verbosity(level.min(u8::MAX as usize) as u8);
+    rest
+}
+
+/// Returns the current verbosity level.
+///
+/// This is the higher of [`set_verbosity`] and the
+/// `CARGO_NEW_RELEASE_VERBOSE` environment variable.
+pub fn verbosity() -> u8 {
+    let from_env = env::var("CARGO_NEW_RELEASE_VERBOSE")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(0);
+    VERBOSITY.load(Ordering::Relaxed).max(from_env)
+}
+
+/// Logs that a command is about to run, returning the start time.
+pub(crate) fn log_start(cmd: &Command) -> Instant {
+    if verbosity() > 0 {
+        let cwd = env::current_dir().unwrap_or_default();
+        let dir = match cmd.get_current_dir() {
+            Some(dir) => cwd.join(dir),
+            None => cwd,
+        };
+        eprintln!(
+            "running `{} {}` in {}",
+            cmd.get_program().to_string_lossy(),
+            cmd.display_args(),
+            dir.display()
+        );
+    }
+    Instant::now()
+}
+
+/// Logs that a command has finished.
+pub(crate) fn log_finish(start: Instant, status: ExitStatus) {
+    if verbosity() > 0 {
+        eprintln!("    finished in {:.2?}: {}", start.elapsed(), status);
+    }
+}
+
+/// Logs captured output of a command.
+pub(crate) fn log_output(name: &str, output: &[u8]) {
+    if verbosity() > 1 && !output.is_empty() {
+        eprintln!("    {}:", name);
+        for line in String::from_utf8_lossy(output).lines() {
+            eprintln!("        {}", line);
+        }
+    }
+}
 
 pub trait CommandExt {
+    /// Creates a `git` command from a whitespace-separated argument string.
+    ///
+    /// Use [`CommandExt::git_args`] for arguments that may contain spaces.
     fn git(args: &str) -> Command;
+    /// Creates a `git` command with the given arguments passed verbatim.
+    fn git_args<I, S>(args: I) -> Command
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>;
+    /// Runs the command, returning its trimmed stdout.
+    ///
+    /// Fails if the command does not exit successfully, with stderr included
+    /// in the error.
     fn run_stdout(&mut self) -> Result<String>;
+    /// Runs the command, returning both stdout and stderr.
+    ///
+    /// Fails if the command does not exit successfully, with stderr included
+    /// in the error.
+    fn run_output(&mut self) -> Result<CommandOutput>;
+    /// Returns the arguments quoted so they can be pasted into a shell.
     fn display_args(&self) -> String;
     fn run_success(&mut self) -> Result<bool>;
 }
 
 impl CommandExt for Command {
     fn git(args: &str) -> Command {
-        // TODO: verbose flag to show commands being run.
-        let vargs: Vec<_> = args.split_whitespace().collect();
+        Command::git_args(args.split_whitespace())
+    }
+
+    fn git_args<I, S>(args: I) -> Command
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
         let mut cmd = Command::new("git");
-        cmd.args(&vargs);
+        cmd.args(args);
         cmd
     }
 
     fn run_stdout(&mut self) -> Result<String> {
+        Ok(self.run_output()?.stdout.trim().to_string())
+    }
+
+    fn run_output(&mut self) -> Result<CommandOutput> {
         self.stdout(Stdio::piped());
-        match self.output() {
-            Ok(output) => {
-                if !output.status.success() {
-                    bail!(
-                        "failed to run `{} {}`: exit status {:?}",
-                        self.get_program().to_str().unwrap(),
-                        self.display_args(),
-                        output.status
-                    );
-                }
-                let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
-                Ok(stdout)
-            }
+        self.stderr(Stdio::piped());
+        let start = log_start(self);
+        let output = match self.output() {
+            Ok(output) => output,
             Err(e) => {
                 bail!(
                     "failed to spawn `{}`: {}",
@@ -40,21 +159,41 @@ impl CommandExt for Command {
                     e
                 );
             }
+        };
+        log_finish(start, output.status);
+        log_output("stdout", &output.stdout);
+        log_output("stderr", &output.stderr);
+        let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+        let stderr = String::from_utf8_lossy(&output.stderr).into_owned();
+        if !output.status.success() {
+            let msg = format!(
+                "failed to run `{} {}`: exit status {:?}",
+                self.get_program().to_str().unwrap(),
+                self.display_args(),
+                output.status
+            );
+            let stderr = stderr.trim();
+            if stderr.is_empty() {
+                bail!(msg);
+            }
+            return Err(format_err!("{}", truncate(stderr, MAX_STDERR_LEN)).context(msg));
         }
+        Ok(CommandOutput { stdout, stderr })
     }
 
     fn display_args(&self) -> String {
         let args: Vec<_> = self
             .get_args()
-            .into_iter()
-            .map(|s| s.to_str().unwrap())
+            .map(|s| shell_quote(&s.to_string_lossy()))
             .collect();
         args.join(" ")
     }
 
     fn run_success(&mut self) -> Result<bool> {
+        let start = log_start(self);
         match self.status() {
             Ok(status) => {
+                log_finish(start, status);
                 if status.code() != Some(0) && status.code() != Some(1) {
                     bail!(
                         "failed to run `{} {}`: exit status {:?}",
@@ -76,21 +215,202 @@ impl CommandExt for Command {
     }
 }
 
-/// Returns Vec of `(pr_num, pr_url, pr_description)` tuples.
-pub fn commits_in_log(log: &str) -> Vec<(u32, String, String)> {
+/// The output of a command that exited successfully.
+#[derive(Clone, Debug)]
+pub struct CommandOutput {
+    pub stdout: String,
+    pub stderr: String,
+}
+
+/// Truncates `s` to its last `max` bytes, noting how much was removed.
+///
+/// The end is kept, as that is usually where a command says what went wrong.
+fn truncate(s: &str, max: usize) -> String {
+    if s.len() <= max {
+        return s.to_string();
+    }
+    let mut start = s.len() - max;
+    while !s.is_char_boundary(start) {
+        start += 1;
+    }
+    format!("... ({} earlier bytes omitted)\n{}", start, &s[start..])
+}
+
+/// Quotes a string for a POSIX shell, if necessary.
+fn shell_quote(s: &str) -> String {
+    let is_safe = |c: char| c.is_ascii_alphanumeric() || "-_./:=@%+,".contains(c);
+    if !s.is_empty() && s.chars().all(is_safe) {
+        s.to_string()
+    } else {
+        format!("'{}'", s.replace('\'', r"'\''"))
+    }
+}
+
+/// A repository on GitHub, such as `rust-lang/cargo`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GitHubRepo {
+    pub owner: String,
+    pub name: String,
+}
+
+impl GitHubRepo {
+    /// Parses an `owner/name` string.
+    pub fn parse(s: &str) -> Result<GitHubRepo> {
+        match s.split_once('/') {
+            Some((owner, name)) if !owner.is_empty() && !name.is_empty() && !name.contains('/') => {
+                Ok(GitHubRepo {
+                    owner: owner.to_string(),
+                    name: name.to_string(),
+                })
+            }
+            _ => bail!("expected repository in `owner/name` format, got `{}`", s),
+        }
+    }
+
+    /// Determines which repository to work on.
+    ///
+    /// This is the value of the `--repo` flag if given, otherwise the
+    /// `cargo-new-release.repo` git config setting, defaulting to
+    /// `rust-lang/cargo`.
+    pub fn from_config(flag: Option<&str>) -> Result<GitHubRepo> {
+        match flag {
+            Some(flag) => GitHubRepo::parse(flag),
+            None => GitHubRepo::parse(
+                &Command::git("config --default rust-lang/cargo --get cargo-new-release.repo")
+                    .run_stdout()?,
+            ),
+        }
+    }
+
+    /// Returns whether the given git remote URL points to this repository.
+    pub fn matches_remote(&self, url: &str) -> bool {
+        let url = url.trim_end_matches('/');
+        let url = url.strip_suffix(".git").unwrap_or(url);
+        url.ends_with(&format!("{}/{}", self.owner, self.name))
+    }
+
+    /// The URL of the repository on GitHub.
+    pub fn url(&self) -> String {
+        format!("https://github.com/{}/{}", self.owner, self.name)
+    }
+
+    /// The base URL for the repository in the GitHub REST API.
+    ///
+    /// The API server can be changed with the `GITHUB_API_URL` environment
+    /// variable, for example to use a test server.
+    pub fn api_url(&self) -> String {
+        let base =
+            env::var("GITHUB_API_URL").unwrap_or_else(|_| "https://api.github.com".to_string());
+        format!(
+            "{}/repos/{}/{}",
+            base.trim_end_matches('/'),
+            self.owner,
+            self.name
+        )
+    }
+
+    /// The URL of the given PR on GitHub.
+    pub fn pr_url(&self, number: u32) -> String {
+        format!("{}/pull/{}", self.url(), number)
+    }
+}
+
+impl fmt::Display for GitHubRepo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.owner, self.name)
+    }
+}
+
+/// How a PR was merged into the branch.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MergeStyle {
+    /// A bors merge commit (`Auto merge of #N - ...`).
+    Bors,
+    /// A GitHub merge commit (`Merge pull request #N from ...`).
+    MergeCommit,
+    /// A squash merge, where the title ends with `(#N)`.
+    Squash,
+}
+
+/// A PR found in the git log.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PullRequest {
+    /// The PR number.
+    pub number: u32,
+    /// The URL of the PR on GitHub.
+    pub url: String,
+    /// The PR title.
+    pub title: String,
+    /// The hash of the commit that merged the PR.
+    pub merge_commit: String,
+    /// Who opened the PR.
+    ///
+    /// For bors and GitHub merge commits this is the GitHub username that
+    /// owns the PR branch. For squash merges, or merge messages that don't
+    /// name the branch, this is the git author name.
+    pub author: String,
+    /// How the PR was merged.
+    pub merge_style: MergeStyle,
+    /// The rest of the commit message after the title.
+    ///
+    /// For bors merges this is the PR description.
+    pub body: String,
+}
+
+/// Returns the PRs merged in the output of `git log --first-parent`.
+///
+/// The log must be in the default `medium` format. Prefer
+/// [`GitBackend::first_parent_log`](git::GitBackend::first_parent_log) with
+/// [`pull_requests`], which does not depend on the user's git configuration.
+pub fn commits_in_log(log: &str, repo: &GitHubRepo) -> Vec<PullRequest> {
     let commit_re = Regex::new("(?m)^commit ").unwrap();
-    let merge_re =
-        Regex::new(r"(?:Auto merge of|Merge pull request) #([0-9]+)|\(#([0-9]+)\)$").unwrap();
-    commit_re
-        .split(&log)
+    let commits: Vec<_> = commit_re
+        .split(log)
         .filter(|commit| !commit.trim().is_empty())
-        .filter_map(|commit| {
+        .map(|commit| {
             let hash = commit.split_whitespace().next().expect("hash");
-            let mut lines = commit
+            let author = commit
+                .lines()
+                .find_map(|line| line.strip_prefix("Author:"))
+                .map(|author| match author.find('<') {
+                    Some(i) => author[..i].trim(),
+                    None => author.trim(),
+                })
+                .unwrap_or_default();
+            let message: Vec<_> = commit
                 .lines()
-                .filter(|line| !line.trim().is_empty() && line.starts_with(' '))
-                .map(|line| line.trim());
-            let first = lines.next().expect("auto");
+                .filter(|line| line.starts_with(' '))
+                .map(|line| line.trim())
+                .collect();
+            git::Commit {
+                hash: hash.to_string(),
+                author: author.to_string(),
+                message: message.join("\n"),
+            }
+        })
+        .collect();
+    pull_requests(&commits, repo)
+}
+
+/// Returns the PRs merged by the given commits.
+///
+/// Commits that don't look like a PR merge are skipped with a warning.
+pub fn pull_requests(commits: &[git::Commit], repo: &GitHubRepo) -> Vec<PullRequest> {
+    let merge_re = Regex::new(
+        r"(?:Auto merge of #([0-9]+)(?: - ([^:\s]+):)?|Merge pull request #([0-9]+)(?: from ([^/\s]+)/)?)|\(#([0-9]+)\)$",
+    )
+    .unwrap();
+    commits
+        .iter()
+        .filter_map(|commit| {
+            let mut lines = commit.message.lines();
+            let mut next_line = || {
+                lines
+                    .find(|line| !line.trim().is_empty())
+                    .unwrap_or_default()
+                    .trim()
+            };
+            let first = next_line();
             let cap = match merge_re.captures(first) {
                 Some(m) => m,
                 None => {
@@ -98,25 +418,49 @@ pub fn commits_in_log(log: &str) -> Vec<(u32, String, String)> {
                         "could not find \"{}\" in line: {}\nhash: {}",
                         merge_re.as_str(),
                         first,
-                        hash
+                        commit.hash
                     );
                     return None;
                 }
             };
-            let (cap, descr) = match (cap.get(1), cap.get(2)) {
-                (Some(cap), _) => (cap, lines.next().unwrap_or_default().to_string()),
-                (_, Some(cap)) => {
+            // Fall back to the git author if the merge message doesn't say
+            // whose branch it was.
+            let author = |i: usize| {
+                cap.get(i)
+                    .map_or_else(|| commit.author.clone(), |m| m.as_str().to_string())
+            };
+            let (num, title, author, merge_style) = match (cap.get(1), cap.get(3), cap.get(5)) {
+                (Some(num), _, _) => (num, next_line().to_string(), author(2), MergeStyle::Bors),
+                (_, Some(num), _) => (
+                    num,
+                    next_line().to_string(),
+                    author(4),
+                    MergeStyle::MergeCommit,
+                ),
+                (_, _, Some(num)) => {
                     let mut pr_title = first.to_string();
                     // Remove `(#    )` part
-                    let range = (cap.range().start - 2)..=(cap.range().end);
+                    let range = (num.range().start - 2)..=(num.range().end);
                     pr_title.replace_range(range, "");
-                    (cap, pr_title.trim_end().to_string())
+                    (
+                        num,
+                        pr_title.trim_end().to_string(),
+                        commit.author.clone(),
+                        MergeStyle::Squash,
+                    )
                 }
-                (None, None) => panic!("cannot found PR number: {first}"),
+                _ => panic!("cannot found PR number: {first}"),
             };
-            let pr_num = cap.as_str().parse::<u32>().expect("digits only");
-            let url = format!("https://github.com/rust-lang/cargo/pull/{}", pr_num);
-            Some((pr_num, url, descr))
+            let number = num.as_str().parse::<u32>().expect("digits only");
+            Some(PullRequest {
+                number,
+                url: repo.pr_url(number),
+                title,
+                merge_commit: commit.hash.clone(),
+                author,
+                merge_style,
+                body: lines.collect::<Vec<_>>().join("\n").trim().to_string(),
+            })
         })
         .collect()
 }
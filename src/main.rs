@@ -1,109 +1,738 @@
-use anyhow::{format_err, Context, Result};
-use cargo_new_release::CommandExt;
-use dialoguer::Confirm;
+use anyhow::{bail, format_err, Context, Result};
+use cargo_new_release::bump::{self, Bump};
+use cargo_new_release::calendar::{self, Calendar};
+use cargo_new_release::category::{self, CATEGORIES};
+use cargo_new_release::changelog::coverage::{self, Coverage};
+use cargo_new_release::changelog::lint::{self, Severity};
+use cargo_new_release::changelog::{Changelog, CompareLink, Entry, Release};
+use cargo_new_release::git::{self, GitBackend};
+use cargo_new_release::github;
+use cargo_new_release::opener::Opener;
+use cargo_new_release::prompt::{self, confirm, confirm_safety, InputMode, PromptPolicy};
+use cargo_new_release::review::{self, ReviewItem};
+use cargo_new_release::rust;
+use cargo_new_release::version_gates::{self, Gate};
+use cargo_new_release::workspace::{self, Workspace};
+use cargo_new_release::{CommandExt, GitHubRepo, PullRequest};
 use regex::Regex;
 use semver::Version;
+use std::collections::BTreeMap;
 use std::env;
 use std::fs;
+use std::io;
+use std::path::Path;
 use std::process::{exit, Command};
 
 const CHANGELOG_PATH: &str = "src/doc/src/CHANGELOG.md";
 
-/// Checks that the repo is ready to go.
-fn check_status() -> Result<()> {
+/// Command-line options.
+struct Options {
+    /// Path to the rust-lang/rust repo.
+    ///
+    /// May be omitted when resuming, in which case the saved path is used.
+    rust_repo: Option<String>,
+    /// If true, print what would be done without modifying anything.
+    ///
+    /// Files are read from the base branch instead of the working tree.
+    dry_run: bool,
+    /// If true, continue from where a previous run stopped.
+    resume: bool,
+    /// Start from the given step instead of the beginning.
+    from_step: Option<Step>,
+    /// The repository being released.
+    repo: GitHubRepo,
+    /// The rust repo's remote for rust-lang/rust.
+    rust_remote: String,
+    /// How PR URLs are opened for review.
+    opener: Opener,
+    /// If true, open each PR for review instead of a single review page.
+    review_tabs: bool,
+    /// How to change the version in `Cargo.toml`.
+    bump: Bump,
+    /// How to change the versions of workspace members, by name, from
+    /// `--bump-member NAME=KIND`.
+    ///
+    /// Members that need a new version and aren't listed get a patch bump.
+    member_bumps: BTreeMap<String, Bump>,
+    /// The branch to create `version-bump` from.
+    ///
+    /// Set from [`default_base`] before any step runs, unless given with
+    /// `--base`.
+    base: Option<String>,
+    /// The Rust release schedule.
+    calendar: Calendar,
+}
+
+impl Options {
+    fn from_args(args: Vec<String>) -> Result<Options> {
+        let mut rust_repo = None;
+        let mut dry_run = false;
+        let mut resume = false;
+        let mut from_step = None;
+        let mut repo = None;
+        let mut rust_remote = None;
+        let mut browser = None;
+        let mut review_tabs = false;
+        let mut bump = Bump::Minor;
+        let mut member_bumps = BTreeMap::new();
+        let mut base = None;
+        let mut input_mode = InputMode::Interactive;
+        let mut prompt_policy = None;
+        let mut schedule_exceptions = None;
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--dry-run" => dry_run = true,
+                "--resume" => resume = true,
+                "--review-tabs" => review_tabs = true,
+                "--yes" => input_mode = InputMode::Yes,
+                "--no-input" => input_mode = InputMode::NoInput,
+                "--prompt-policy" => {
+                    prompt_policy = Some(
+                        args.next()
+                            .ok_or_else(|| format_err!("--prompt-policy requires a path"))?,
+                    );
+                }
+                "--from-step" => {
+                    let name = args
+                        .next()
+                        .ok_or_else(|| format_err!("--from-step requires a step name"))?;
+                    from_step = Some(Step::from_name(&name)?);
+                }
+                "--repo" => {
+                    repo = Some(
+                        args.next()
+                            .ok_or_else(|| format_err!("--repo requires `owner/name`"))?,
+                    );
+                }
+                "--rust-remote" => {
+                    rust_remote = Some(
+                        args.next()
+                            .ok_or_else(|| format_err!("--rust-remote requires a remote name"))?,
+                    );
+                }
+                "--schedule-exceptions" => {
+                    schedule_exceptions = Some(
+                        args.next()
+                            .ok_or_else(|| format_err!("--schedule-exceptions requires a path"))?,
+                    );
+                }
+                "--browser" => {
+                    browser = Some(
+                        args.next()
+                            .ok_or_else(|| format_err!("--browser requires a command"))?,
+                    );
+                }
+                "--bump" => {
+                    let kind = args.next().ok_or_else(|| {
+                        format_err!("--bump requires `major`, `minor`, `patch`, or a version")
+                    })?;
+                    bump = Bump::parse(&kind)?;
+                }
+                "--bump-member" => {
+                    let value = args.next().ok_or_else(|| {
+                        format_err!("--bump-member requires `NAME=KIND`, like `crates-io=minor`")
+                    })?;
+                    let (name, kind) = value.split_once('=').ok_or_else(|| {
+                        format_err!(
+                            "--bump-member requires `NAME=KIND`, like `crates-io=minor`, got `{}`",
+                            value
+                        )
+                    })?;
+                    let kind = Bump::parse(kind)
+                        .with_context(|| format_err!("invalid --bump-member for `{}`", name))?;
+                    member_bumps.insert(name.to_string(), kind);
+                }
+                "--base" => {
+                    base = Some(
+                        args.next()
+                            .ok_or_else(|| format_err!("--base requires a branch"))?,
+                    );
+                }
+                s if s.starts_with('-') => bail!("unknown option `{}`", s),
+                _ if rust_repo.is_none() => rust_repo = Some(arg),
+                _ => bail!("unexpected argument `{}`", arg),
+            }
+        }
+        prompt::set_input_mode(input_mode);
+        if let Some(path) = prompt_policy {
+            prompt::set_policy(PromptPolicy::load(Path::new(&path))?);
+        }
+        if resume && from_step.is_some() {
+            bail!("--resume and --from-step cannot be used together");
+        }
+        Ok(Options {
+            rust_repo,
+            dry_run,
+            resume,
+            from_step,
+            repo: GitHubRepo::from_config(repo.as_deref())?,
+            rust_remote: rust::remote_from_config(rust_remote.as_deref())?,
+            opener: Opener::from_config(browser.as_deref())?,
+            review_tabs,
+            bump,
+            member_bumps,
+            base,
+            calendar: Calendar::from_config(schedule_exceptions.as_deref())?,
+        })
+    }
+}
+
+/// A step of the release process.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Step {
+    CheckStatus,
+    CreateBranch,
+    BumpVersion,
+    WaitForInspection,
+    CommitBump,
+    PrepChangelog,
+    ReviewNightly,
+    ReviewBeta,
+    LintChangelog,
+    CommitChangelog,
+    CreatePr,
+}
+
+impl Step {
+    /// All steps, in the order they are run.
+    const ALL: [Step; 11] = [
+        Step::CheckStatus,
+        Step::CreateBranch,
+        Step::BumpVersion,
+        Step::WaitForInspection,
+        Step::CommitBump,
+        Step::PrepChangelog,
+        Step::ReviewNightly,
+        Step::ReviewBeta,
+        Step::LintChangelog,
+        Step::CommitChangelog,
+        Step::CreatePr,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Step::CheckStatus => "check-status",
+            Step::CreateBranch => "create-branch",
+            Step::BumpVersion => "bump-version",
+            Step::WaitForInspection => "wait-for-inspection",
+            Step::CommitBump => "commit-bump",
+            Step::PrepChangelog => "prep-changelog",
+            Step::ReviewNightly => "review-nightly",
+            Step::ReviewBeta => "review-beta",
+            Step::LintChangelog => "lint-changelog",
+            Step::CommitChangelog => "commit-changelog",
+            Step::CreatePr => "create-pr",
+        }
+    }
+
+    /// Returns whether the step prepares, reviews, or commits the changelog.
+    fn is_changelog(self) -> bool {
+        matches!(
+            self,
+            Step::PrepChangelog
+                | Step::ReviewNightly
+                | Step::ReviewBeta
+                | Step::LintChangelog
+                | Step::CommitChangelog
+        )
+    }
+
+    fn from_name(name: &str) -> Result<Step> {
+        Step::ALL
+            .into_iter()
+            .find(|step| step.name() == name)
+            .ok_or_else(|| {
+                let names: Vec<_> = Step::ALL.iter().map(|step| step.name()).collect();
+                format_err!(
+                    "unknown step `{}`, expected one of: {}",
+                    name,
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+/// State saved in `.git` before each step so that an aborted run can be
+/// resumed with `--resume`.
+struct Progress {
+    /// The step to run next.
+    next_step: Step,
+    /// Path to the rust-lang/rust repo.
+    rust_repo: String,
+    /// The version being released, once `bump-version` has run.
+    next_version: Option<Version>,
+    /// URLs to open to review the nightly section of the changelog.
+    nightly_urls: Vec<String>,
+    /// URLs to open to review the beta section of the changelog.
+    beta_urls: Vec<String>,
+    /// The changelog `prep-changelog` would have written with `--dry-run`.
+    ///
+    /// This is not saved, as a dry run can't be resumed.
+    dry_run_changelog: Option<String>,
+}
+
+impl Progress {
+    fn new(rust_repo: String) -> Progress {
+        Progress {
+            next_step: Step::CheckStatus,
+            rust_repo,
+            next_version: None,
+            nightly_urls: Vec::new(),
+            beta_urls: Vec::new(),
+            dry_run_changelog: None,
+        }
+    }
+
+    fn path() -> Result<String> {
+        Command::git("rev-parse --git-path cargo-new-release-progress.json").run_stdout()
+    }
+
+    /// Loads the progress of a previous run, if there is one.
+    fn load() -> Result<Option<Progress>> {
+        let path = Progress::path()?;
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e).with_context(|| format_err!("failed to read {}", path)),
+        };
+        let json: serde_json::Value = serde_json::from_str(&contents)
+            .with_context(|| format_err!("failed to parse {}", path))?;
+        let field = |key: &str| {
+            json[key]
+                .as_str()
+                .ok_or_else(|| format_err!("{} is missing `{}`", path, key))
+        };
+        let urls = |key: &str| -> Vec<String> {
+            json[key]
+                .as_array()
+                .map(|urls| {
+                    urls.iter()
+                        .filter_map(|url| url.as_str().map(String::from))
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+        let next_version = match json["next_version"].as_str() {
+            Some(v) => Some(Version::parse(v)?),
+            None => None,
+        };
+        Ok(Some(Progress {
+            next_step: Step::from_name(field("next_step")?)?,
+            rust_repo: field("rust_repo")?.to_string(),
+            next_version,
+            nightly_urls: urls("nightly_urls"),
+            beta_urls: urls("beta_urls"),
+            dry_run_changelog: None,
+        }))
+    }
+
+    fn save(&self) -> Result<()> {
+        let json = serde_json::json!({
+            "next_step": self.next_step.name(),
+            "rust_repo": self.rust_repo,
+            "next_version": self.next_version.as_ref().map(|v| v.to_string()),
+            "nightly_urls": self.nightly_urls,
+            "beta_urls": self.beta_urls,
+        });
+        let path = Progress::path()?;
+        fs::write(&path, serde_json::to_string_pretty(&json)?)
+            .with_context(|| format_err!("failed to write {}", path))
+    }
+
+    /// Removes the saved progress once the release is finished.
+    fn remove() -> Result<()> {
+        let path = Progress::path()?;
+        match fs::remove_file(&path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => {
+                Err(e).with_context(|| format_err!("failed to remove {}", path))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns the version being released.
+    ///
+    /// If starting after `bump-version` without saved progress, this is
+    /// whatever version `Cargo.toml` already has. A dry run never bumped
+    /// it, so this is the version `bump-version` would have written.
+    fn next_version(&mut self, opts: &Options) -> Result<Version> {
+        if self.next_version.is_none() {
+            let toml = read_file(opts, "Cargo.toml")?;
+            let mut version = workspace::version(&toml).context("failed to read Cargo.toml")?;
+            if opts.dry_run {
+                version = opts.bump.apply(&version);
+            }
+            self.next_version = Some(version);
+        }
+        Ok(self.next_version.clone().unwrap())
+    }
+}
+
+/// Runs a command that modifies something.
+///
+/// With `--dry-run` the command is only printed.
+fn run_mutating(opts: &Options, cmd: &mut Command) -> Result<bool> {
+    if opts.dry_run {
+        let dir = match cmd.get_current_dir() {
+            Some(dir) => format!(" (in {})", dir.display()),
+            None => String::new(),
+        };
+        eprintln!(
+            "dry-run: would run `{} {}`{}",
+            cmd.get_program().to_str().unwrap(),
+            cmd.display_args(),
+            dir
+        );
+        return Ok(true);
+    }
+    cmd.run_success()
+}
+
+/// Reads a file from the repo.
+///
+/// With `--dry-run` the file is read from the base branch, since that is
+/// what the `version-bump` branch would have been created from.
+fn read_file(opts: &Options, path: &str) -> Result<String> {
+    if opts.dry_run {
+        return git::open(Path::new("."))?.show_file(tree_rev(opts), path);
+    }
+    fs::read_to_string(path).with_context(|| format_err!("failed to read {}", path))
+}
+
+/// Returns the revision whose files [`read_file`] reads.
+fn tree_rev(opts: &Options) -> &str {
+    if opts.dry_run {
+        base(opts)
+    } else {
+        "HEAD"
+    }
+}
+
+/// Returns the branch to create `version-bump` from.
+fn base(opts: &Options) -> &str {
+    opts.base
+        .as_deref()
+        .expect("the base branch is set before running steps")
+}
+
+/// Returns the branch to release from when `--base` isn't given.
+///
+/// Patch releases are made from the release branch of the current beta,
+/// where fixes are backported. Everything else is released from master.
+fn default_base(opts: &Options, rust_repo: &str) -> Result<String> {
+    if !opts.bump.is_patch() {
+        return Ok("upstream/master".to_string());
+    }
+    let beta = rust::version_at(
+        &*git::open(Path::new(rust_repo))?,
+        &format!("{}/beta", opts.rust_remote),
+    )?;
+    Ok(format!("upstream/rust-1.{}.0", beta.minor))
+}
+
+/// Writes a file in the repo.
+///
+/// With `--dry-run` a diff of the changes is displayed instead.
+fn write_file(opts: &Options, path: &str, old: &str, new: &str) -> Result<()> {
+    if opts.dry_run {
+        let tmp = env::temp_dir().join(format!("cargo-new-release-{}", std::process::id()));
+        let name = Path::new(path).file_name().unwrap();
+        let (a, b) = (tmp.join("a").join(name), tmp.join("b").join(name));
+        for (p, contents) in [(&a, old), (&b, new)] {
+            fs::create_dir_all(p.parent().unwrap())?;
+            fs::write(p, contents)?;
+        }
+        eprintln!("dry-run: would write {}:", path);
+        let name = name.to_string_lossy();
+        let result = Command::git_args([
+            "--no-pager",
+            "diff",
+            "--no-index",
+            "--no-prefix",
+            "--",
+            &format!("a/{}", name),
+            &format!("b/{}", name),
+        ])
+        .current_dir(&tmp)
+        .run_success();
+        fs::remove_dir_all(&tmp)?;
+        result?;
+        return Ok(());
+    }
+    fs::write(path, new).with_context(|| format_err!("failed to write {}", path))
+}
+
+/// Changes the current directory to the root of the cargo repo.
+fn goto_root() -> Result<()> {
     let root = Command::git("rev-parse --show-toplevel").run_stdout()?;
     env::set_current_dir(root)?;
-    if !Command::git("diff-index --quiet HEAD .").run_success()? {
+    Ok(())
+}
+
+/// Checks that the repo is ready to go.
+fn check_status(opts: &Options) -> Result<()> {
+    if git::open(Path::new("."))?.is_dirty()? {
         eprintln!("Working tree has changes.");
         Command::git("status --porcelain").run_success()?;
-        if !Confirm::new()
-            .with_prompt("Do you want to continue?")
-            .default(false)
-            .interact()?
-        {
+        if opts.dry_run {
+            eprintln!("dry-run: ignoring changes, the working tree will not be touched");
+        } else if !confirm_safety("dirty-tree", "Do you want to continue?", false)? {
             exit(1);
         }
     }
     // Check repo looks correct.
     let upstream = Command::git("config remote.upstream.url").run_stdout()?;
-    if !upstream.ends_with("rust-lang/cargo.git") {
+    if !opts.repo.matches_remote(&upstream) {
         eprintln!(
-            "error: upstream does not appear to be rust-lang/cargo, was: {}",
-            upstream
+            "error: upstream does not appear to be {}, was: {}",
+            opts.repo, upstream
         );
         exit(1);
     }
     let origin = Command::git("config remote.origin.url").run_stdout()?;
-    if !origin.ends_with("/cargo.git") {
-        eprintln!("error: origin does not appear to be cargo, was: {}", origin);
+    let origin_re =
+        Regex::new(&format!(r"/{}(\.git)?/?$", regex::escape(&opts.repo.name))).unwrap();
+    if !origin_re.is_match(&origin) {
+        eprintln!(
+            "error: origin does not appear to be {}, was: {}",
+            opts.repo.name, origin
+        );
         exit(1);
     }
     Ok(())
 }
 
 /// Creates the `version-bump` branch.
-fn create_branch() -> Result<()> {
-    if !Command::git("fetch upstream --tags").run_success()? {
+fn create_branch(opts: &Options) -> Result<()> {
+    if !run_mutating(opts, &mut Command::git("fetch upstream --tags"))? {
         eprintln!("error: failed to fetch upstream");
         exit(1);
     }
+    let base = base(opts);
+    if git::open(Path::new("."))?.rev_parse(base).is_err() {
+        bail!(
+            "cannot create the version-bump branch from `{}`, which does not exist",
+            base
+        );
+    }
     // Check if branch exists, and delete it if it does.
     if Command::git("show-ref --verify --quiet refs/heads/version-bump").run_success()? {
         eprintln!("info: removing version-bump branch");
     }
-    eprintln!("info: creating version-bump branch");
-    if !Command::git("checkout -B version-bump upstream/master").run_success()? {
+    eprintln!("info: creating version-bump branch from {}", base);
+    if !run_mutating(
+        opts,
+        &mut Command::git_args(["checkout", "-B", "version-bump", base]),
+    )? {
         eprintln!("error: failed to create branch");
         exit(1);
     }
-    if !Command::git("config branch.version-bump.remote origin").run_success()? {
+    if !run_mutating(
+        opts,
+        &mut Command::git("config branch.version-bump.remote origin"),
+    )? {
         eprintln!("error: failed to set remote origin");
         exit(1);
     }
-    if !Command::git("config branch.version-bump.merge refs/heads/version-bump").run_success()? {
+    if !run_mutating(
+        opts,
+        &mut Command::git("config branch.version-bump.merge refs/heads/version-bump"),
+    )? {
         eprintln!("error: failed to set branch merge");
         exit(1);
     }
     Ok(())
 }
 
-/// Updates the version in `Cargo.toml`.
-fn bump_version_toml() -> Result<Version> {
-    // TODO: run some validation if dependent crates like crates-io need to be updated.
-    let mut toml = fs::read_to_string("Cargo.toml")
-        .with_context(|| format_err!("failed to read Cargo.toml"))?;
-    let version_start = toml.find("\nversion = \"").expect("version") + 12;
-    let len = toml[version_start..].find('"').expect("version end");
-    let version = Version::parse(&toml[version_start..version_start + len]).expect("valid version");
-    assert_eq!(version.major, 0);
-    let next_version = Version::new(0, version.minor + 1, 0);
-    toml.replace_range(
-        version_start..version_start + len,
-        &next_version.to_string(),
-    );
-    fs::write("Cargo.toml", toml)?;
+/// Updates the version in `Cargo.toml`, and `Cargo.lock` to match.
+///
+/// The new version is checked against the existing tags, which
+/// `create-branch` fetched. Workspace members that changed since the
+/// previous release are bumped too, along with the requirements on them.
+///
+/// Only the entries of the bumped packages are changed in `Cargo.lock`, so
+/// no registry is needed. Anything else out of date in it is an error, as
+/// it would end up in the bump commit.
+fn bump_version_toml(opts: &Options) -> Result<Version> {
+    let orig = read_file(opts, "Cargo.toml")?;
+    let version = workspace::version(&orig).context("failed to read Cargo.toml")?;
+    let next_version = opts.bump.apply(&version);
+    let repo = git::open(Path::new("."))?;
+    let tags = repo.tags()?;
+    bump::validate(&version, &next_version, &tags)
+        .with_context(|| format_err!("cannot bump version with `--bump {}`", opts.bump))?;
+    eprintln!("info: bumping version from {} to {}", version, next_version);
+
+    let rev = tree_rev(opts);
+    let workspace = Workspace::load(&*repo, rev)?;
+    let has_lock = repo.tree_entry(rev, "Cargo.lock")?.is_some();
+    if has_lock {
+        let drift = workspace::lockfile_drift(&read_file(opts, "Cargo.lock")?, &workspace)?;
+        if !drift.is_empty() {
+            bail!(
+                "Cargo.lock does not match the workspace before the version bump:\n  {}\n\
+                 update it on master before releasing",
+                drift.join("\n  ")
+            );
+        }
+    }
+    // Members inheriting the workspace's version are bumped along with the
+    // root package when it inherits it too.
+    let root_inherits = workspace.root().is_none_or(|root| root.inherits_version);
+    let mut bumped: Vec<_> = workspace
+        .members
+        .iter()
+        .filter(|m| m.dir.is_empty() || (root_inherits && m.inherits_version))
+        .map(|m| (m.name.clone(), next_version.clone()))
+        .collect();
+    bumped.extend(bump_members(opts, &*repo, &workspace, &version, &tags)?);
+
+    let toml = workspace::set_version(&orig, &next_version)?;
+    let toml = workspace::update_requirements(&toml, &bumped)?;
+    write_file(opts, "Cargo.toml", &orig, &toml)?;
+    for member in workspace.members.iter().filter(|m| !m.dir.is_empty()) {
+        let path = member.manifest_path();
+        let orig = read_file(opts, &path)?;
+        let mut toml = orig.clone();
+        let bump = bumped.iter().find(|(name, _)| *name == member.name);
+        if let Some((_, version)) = bump.filter(|_| !member.inherits_version) {
+            toml = workspace::set_version(&toml, version)
+                .with_context(|| format_err!("failed to bump {}", path))?;
+        }
+        let toml = workspace::update_requirements(&toml, &bumped)?;
+        if toml != orig {
+            write_file(opts, &path, &orig, &toml)?;
+        }
+    }
+    if has_lock {
+        let orig = read_file(opts, "Cargo.lock")?;
+        let lock = workspace::update_lockfile(&orig, &bumped)?;
+        write_file(opts, "Cargo.lock", &orig, &lock)?;
+    }
     Ok(next_version)
 }
 
-/// Waits for the user to manually validate.
-fn wait_for_inspection() -> Result<()> {
-    eprintln!("Check for any tests or rustc probing (usually target_info.rs) that can be updated.");
-    if !Confirm::new()
-        .with_prompt("Ready to commit?")
-        .default(true)
-        .interact()?
-    {
+/// Proposes a bump for each workspace member that changed since the
+/// release before `version` without its version changing.
+///
+/// Members get the kind of bump given with `--bump-member`, or a patch bump.
+///
+/// Returns the names and new versions of the members to bump.
+fn bump_members(
+    opts: &Options,
+    repo: &dyn GitBackend,
+    workspace: &Workspace,
+    version: &Version,
+    tags: &[String],
+) -> Result<Vec<(String, Version)>> {
+    for name in opts.member_bumps.keys() {
+        if !workspace.members.iter().any(|member| member.name == *name) {
+            bail!("--bump-member `{}` is not a workspace member", name);
+        }
+    }
+    if workspace.members.iter().all(|member| member.dir.is_empty()) {
+        return Ok(Vec::new());
+    }
+    let tag = match bump::previous_tag(version, tags) {
+        Some(tag) => tag,
+        None => {
+            eprintln!(
+                "warning: no release tag before {} found, \
+                 cannot tell which workspace members need a new version",
+                version
+            );
+            return Ok(Vec::new());
+        }
+    };
+    let rev = tree_rev(opts);
+    let unbumped = workspace.unbumped(repo, tag, rev)?;
+    if unbumped.is_empty() {
+        return Ok(Vec::new());
+    }
+    eprintln!(
+        "Workspace members changed since {} without a new version:",
+        tag
+    );
+    let mut bumped = Vec::new();
+    for member in unbumped {
+        let old = member.version.as_ref().unwrap();
+        let new = opts
+            .member_bumps
+            .get(&member.name)
+            .unwrap_or(&Bump::Patch)
+            .apply(old);
+        eprintln!("  {} {} -> {} ({})", member.name, old, new, member.dir);
+        bumped.push((member.name.clone(), new));
+    }
+    if !opts.dry_run && !confirm("bump-members", "Bump these crates?", true)? {
+        eprintln!("warning: not bumping workspace members, remember to bump them by hand");
+        return Ok(Vec::new());
+    }
+    Ok(bumped)
+}
+
+/// Waits for the user to check code that depends on the Rust version.
+///
+/// Lines that may need updating now that the version on the rust repo's
+/// stable branch is out are listed as a checklist. Nightly-only code is
+/// listed in a group of its own, as it doesn't depend on the version.
+fn wait_for_inspection(opts: &Options, rust_repo: &str) -> Result<()> {
+    let stable = rust::version_at(
+        &*git::open(Path::new(rust_repo))?,
+        &format!("{}/stable", opts.rust_remote),
+    )?
+    .minor;
+    let (nightly, gates): (Vec<_>, Vec<_>) = version_gates::search(tree_rev(opts), stable)?
+        .into_iter()
+        .partition(Gate::is_nightly);
+    if !gates.is_empty() {
+        eprintln!(
+            "Check whether these can be updated now that Rust 1.{} is stable:",
+            stable
+        );
+        for gate in &gates {
+            eprintln!("  [ ] {}", gate);
+        }
+    }
+    if !nightly.is_empty() {
+        eprintln!(
+            "Check whether these nightly-only tests and checks cover anything \
+             stabilized in 1.{}:",
+            stable
+        );
+        for gate in &nightly {
+            eprintln!("  [ ] {}", gate);
+        }
+    }
+    if gates.is_empty() {
+        eprintln!("No version-dependent code found.");
+    }
+    eprintln!("Check for any other rustc probing (usually target_info.rs) that can be updated.");
+    if opts.dry_run {
+        return Ok(());
+    }
+    if !confirm("bump-inspected", "Ready to commit?", true)? {
         exit(1);
     }
     Ok(())
 }
 
-/// Commits the version bump.
-fn commit_bump(next_version: &Version) -> Result<()> {
-    if !Command::git("commit -a -m")
-        .arg(format!("Bump to {}", next_version))
-        .run_success()?
-    {
+/// Returns whether releasing `next_version` adds a section to the changelog.
+///
+/// The changelog follows Rust's releases, so only a new `0.N.0` (shipped
+/// with Rust `1.(N-1)`) gets one. Patch and major bumps only bump the
+/// version.
+fn updates_changelog(next_version: &Version) -> bool {
+    next_version.major == 0 && next_version.patch == 0 && next_version.pre.is_empty()
+}
+
+/// Commits the version bump, including `Cargo.lock`.
+fn commit_bump(opts: &Options, next_version: &Version) -> Result<()> {
+    if !run_mutating(
+        opts,
+        &mut Command::git_args(["commit", "-a", "-m", &format!("Bump to {}", next_version)]),
+    )? {
         eprintln!("error: failed to commit");
         exit(1);
     }
@@ -111,38 +740,60 @@ fn commit_bump(next_version: &Version) -> Result<()> {
 }
 
 /// Modifies `CHANGELOG.md` to include stubs for the given version.
-fn prep_changelog(next_version: &Version, rust_repo: &str) -> Result<()> {
-    let beta_minor_version = next_version.minor - 2;
+///
+/// New PRs are sorted into the `### Added`, `### Changed`, `### Fixed`, and
+/// `### Nightly only` sections by [`category::categorize`]. PRs that can't be
+/// sorted are listed above `### Added` for a human to move.
+///
+/// Saves the URLs to open to review the nightly and beta versions in
+/// `progress`. These are the review pages, or the PRs themselves with
+/// `--review-tabs`.
+///
+/// Nothing is changed if the changelog already has a section for the new
+/// nightly version, so that rerunning this step after it wrote the
+/// changelog doesn't add another.
+fn prep_changelog(opts: &Options, progress: &mut Progress, next_version: &Version) -> Result<()> {
+    let rust_repo = progress.rust_repo.as_str();
     // Determine the version in rust-lang/rust beta branch.
-    if !Command::git("fetch upstream --tags")
-        .current_dir(rust_repo)
-        .run_success()?
-    {
+    if !run_mutating(
+        opts,
+        Command::git_args(["fetch", &opts.rust_remote, "--tags"]).current_dir(rust_repo),
+    )? {
         eprintln!("error: failed to fetch rust upstream");
         exit(1);
     }
-    let last_beta_line = Command::git("ls-tree upstream/beta src/tools/cargo")
-        .current_dir(rust_repo)
-        .run_stdout()?;
-    let mut parts = last_beta_line.split_whitespace();
-    assert_eq!(parts.next(), Some("160000"));
-    assert_eq!(parts.next(), Some("commit"));
-    let last_beta_hash = parts.next().expect("hash");
-    assert_eq!(parts.next(), Some("src/tools/cargo"));
-
-    // Determine the rust-lang/cargo beta version.
-    let last_branch_line = Command::git(&format!(
-        "show-ref upstream/rust-1.{}.0",
-        beta_minor_version
-    ))
-    .run_stdout()?;
-    let last_branch_hash = last_branch_line.split_whitespace().next().expect("hash");
+    let beta_minor_version = rust_beta_version(opts, rust_repo, next_version)?;
+    let orig = read_file(opts, CHANGELOG_PATH)?;
+    let mut changelog = Changelog::parse(&orig);
+    if changelog.releases.first().is_some_and(|release| {
+        release.minor() == Some(beta_minor_version + 1)
+            && release.compare.as_ref().is_some_and(|c| c.end() == "HEAD")
+    }) {
+        eprintln!(
+            "info: {} already has a section for 1.{}, not preparing it again",
+            CHANGELOG_PATH,
+            beta_minor_version + 1
+        );
+        if opts.dry_run {
+            progress.dry_run_changelog = Some(orig);
+        }
+        return Ok(());
+    }
+    let last_beta = rust::cargo_commit_at(
+        &*git::open(Path::new(rust_repo))?,
+        &format!("{}/beta", opts.rust_remote),
+    )?;
+    let last_beta_hash = last_beta.as_str();
+
+    // Determine the beta version of the repo being released.
+    let last_branch_hash = git::open(Path::new("."))?
+        .rev_parse(&format!("upstream/rust-1.{}.0", beta_minor_version))?;
 
     if last_beta_hash != last_branch_hash {
         eprintln!(
             "warning: rust-lang/rust beta branch hash {} does not equal \
-            rust-lang/cargo upstream/rust-1.{}.0 hash {}",
-            last_beta_hash, beta_minor_version, last_branch_hash
+            {} upstream/rust-1.{}.0 hash {}",
+            last_beta_hash, opts.repo, beta_minor_version, last_branch_hash
         );
         eprintln!(
             "This may happen if changes are pushed to rust-1.{}.0 shortly after the beta \
@@ -150,153 +801,537 @@ fn prep_changelog(next_version: &Version, rust_repo: &str) -> Result<()> {
             ",
             beta_minor_version
         );
-        if !Confirm::new()
-            .with_prompt("Do you want to continue?")
-            .default(true)
-            .interact()?
+        if !opts.dry_run && !confirm_safety("beta-hash-mismatch", "Do you want to continue?", true)?
         {
             exit(1);
         }
     }
     let start_of_beta_short_hash = &last_beta_hash[..8];
 
-    let to_links = |prs: &[(u32, String, String)]| -> String {
-        prs.iter()
-            .map(|(num, url, descr)| format!("- {} \n  [#{}]({})\n", descr, num, url))
-            .collect::<Vec<_>>()
-            .join("")
-    };
-
     // Update last version.
-    let changelog = fs::read_to_string(CHANGELOG_PATH)
-        .with_context(|| format_err!("failed to read CHANGELOG.md"))?;
-
-    let head_re = Regex::new(r"([a-f0-9]+)\.\.\.HEAD").unwrap();
-    let matches: Vec<_> = head_re.captures_iter(&changelog).collect();
-    assert_eq!(matches.len(), 2);
-    assert_eq!(
-        matches[0].get(0).unwrap().as_str(),
-        matches[1].get(0).unwrap().as_str()
-    );
-    let beta_hash_start = matches[0].get(1).unwrap().as_str();
-    let beta_version = format!("rust-1.{}.0", beta_minor_version);
-    let mut changelog = head_re
-        .replace_all(
-            &changelog,
-            format!("{}...{}", beta_hash_start, beta_version).as_str(),
+    let beta_release = changelog
+        .releases
+        .first_mut()
+        .ok_or_else(|| format_err!("{} does not have any releases", CHANGELOG_PATH))?;
+    let (line, title) = (beta_release.line, beta_release.title().to_string());
+    let compare = beta_release.compare.as_mut().ok_or_else(|| {
+        format_err!(
+            "{}:{}: expected a compare link after `{}`",
+            CHANGELOG_PATH,
+            line,
+            title
         )
-        .into_owned();
+    })?;
+    if compare.end() != "HEAD" {
+        bail!(
+            "{}:{}: expected the latest compare link to end at HEAD, got `{}`",
+            CHANGELOG_PATH,
+            compare.line,
+            compare.end()
+        );
+    }
+    let beta_hash_start = compare.start().to_string();
+    let beta_version = format!("rust-1.{}.0", beta_minor_version);
+    compare.set_end(&beta_version);
 
     // Determine changes in master (nightly).
-    let master_prs = find_prs(&changelog, start_of_beta_short_hash, "upstream/master")?;
+    let master_prs = find_prs(
+        opts,
+        &changelog,
+        start_of_beta_short_hash,
+        "upstream/master",
+    )?;
+    let master_items = review_items(opts, &master_prs)?;
     // Determine changes in beta.
     let beta_prs = find_prs(
+        opts,
         &changelog,
-        beta_hash_start,
+        &beta_hash_start,
         &format!("upstream/{}", beta_version),
     )?;
+    let beta_items = review_items(opts, &beta_prs)?;
 
-    let added_idx = changelog.find("### Added\n").expect("couldn't find added");
-    changelog.insert_str(added_idx, &to_links(&beta_prs));
+    // Sort beta PRs into the existing sections of the beta version.
+    add_entries(&mut changelog.releases[0], &beta_items);
 
     // Insert new version.
-    assert!(changelog.starts_with("# Changelog\n"));
-    changelog.insert_str(
-        12,
-        &format!(
-            "\n## Cargo 1.{} ({DATE})\n\
-        [{HASH}...HEAD](https://github.com/rust-lang/cargo/compare/{HASH}...HEAD)\n\
-        \n\
-        {LINKS}\n\
-        \n\
-        ### Added\n\
-        \n\
-        ### Changed\n\
-        \n\
-        ### Fixed\n\
-        \n\
-        ### Nightly only\n\
-        \n\
-        ",
-            next_version.minor - 1,
-            HASH = start_of_beta_short_hash,
-            LINKS = to_links(&master_prs),
-            DATE = next_version_date(next_version),
-        ),
+    let mut release = Release::new(
+        &format!("1.{}", next_version.minor - 1),
+        &opts
+            .calendar
+            .release_date(next_version.minor - 1)?
+            .format(calendar::DATE_FORMAT),
+        CompareLink::new(&opts.repo.url(), start_of_beta_short_hash, "HEAD"),
+        &CATEGORIES,
     );
-    fs::write(CHANGELOG_PATH, changelog)?;
+    add_entries(&mut release, &master_items);
+    changelog.releases.insert(0, release);
+    let new = changelog.to_string();
+    write_file(opts, CHANGELOG_PATH, &orig, &new)?;
+    if opts.dry_run {
+        progress.dry_run_changelog = Some(new);
+    }
 
-    let master_urls: Vec<_> = master_prs
-        .iter()
-        .map(|(_pr, url, _descr)| url.as_str())
-        .collect();
-    open_browser(&master_urls)?;
+    if opts.review_tabs {
+        let to_urls =
+            |prs: Vec<PullRequest>| -> Vec<String> { prs.into_iter().map(|pr| pr.url).collect() };
+        progress.nightly_urls = to_urls(master_prs);
+        progress.beta_urls = to_urls(beta_prs);
+        return Ok(());
+    }
+    let nightly_url = write_review_page(
+        opts,
+        "nightly",
+        &format!("Cargo 1.{} (nightly) review", next_version.minor - 1),
+        &master_items,
+    )?;
+    let beta_url = write_review_page(
+        opts,
+        "beta",
+        &format!("Cargo 1.{} (beta) review", beta_minor_version),
+        &beta_items,
+    )?;
+    progress.nightly_urls = vec![nightly_url];
+    progress.beta_urls = vec![beta_url];
+    Ok(())
+}
+
+/// Returns the minor version of Rust on rust-lang/rust's beta branch.
+///
+/// Cargo `0.N` ships with Rust `1.(N-1)`, so bumping cargo to `next_version`
+/// should leave Rust `1.(N-2)` on beta and `1.(N-3)` on stable. A skipped or
+/// delayed release breaks that arithmetic, so `src/version` on both branches
+/// is checked against it rather than trusted blindly.
+fn rust_beta_version(opts: &Options, rust_repo: &str, next_version: &Version) -> Result<u64> {
+    let repo = git::open(Path::new(rust_repo))?;
+    let beta = rust::version_at(&*repo, &format!("{}/beta", opts.rust_remote))?;
+    let stable = rust::version_at(&*repo, &format!("{}/stable", opts.rust_remote))?;
+    let expected = next_version.minor.checked_sub(2).filter(|&minor| minor > 0);
+    match expected {
+        Some(minor) if beta.minor == minor && stable.minor + 1 == minor => Ok(minor),
+        Some(minor) => bail!(
+            "rust-lang/rust has {} on beta and {} on stable, \
+             but cargo {} expects 1.{}.0 on beta and 1.{}.0 on stable\n\
+             Check whether a Rust release was skipped or delayed before continuing.",
+            beta,
+            stable,
+            next_version,
+            minor,
+            minor - 1
+        ),
+        None => bail!(
+            "cannot tell which Rust version the beta of {} is",
+            next_version
+        ),
+    }
+}
+
+/// Adds entries for the given PRs to their sections of the release.
+///
+/// PRs without a category, or whose section is missing, are listed before
+/// the first section.
+fn add_entries(release: &mut Release, items: &[ReviewItem]) {
+    for item in items {
+        let entry = Entry::new(&item.pr.title, item.pr.number, &item.pr.url);
+        match item
+            .category
+            .and_then(|category| release.section_mut(category))
+        {
+            Some(section) => section.push(entry),
+            None => release.push_intro(entry),
+        }
+    }
+}
+
+/// Gathers the details of PRs needed for review and for sorting them into
+/// changelog sections.
+///
+/// Labels are included if `GITHUB_TOKEN` is set.
+fn review_items(opts: &Options, prs: &[PullRequest]) -> Result<Vec<ReviewItem>> {
+    let repo = git::open(Path::new("."))?;
+    let client = github::Client::from_env();
+    prs.iter()
+        .map(|pr| {
+            let labels = match &client {
+                Some(client) => match client.labels(&opts.repo, pr.number) {
+                    Ok(labels) => Some(labels),
+                    Err(e) => {
+                        eprintln!("warning: failed to get labels for #{}: {}", pr.number, e);
+                        None
+                    }
+                },
+                None => None,
+            };
+            let category = category::categorize(pr, labels.as_deref().unwrap_or_default());
+            Ok(ReviewItem {
+                pr: pr.clone(),
+                labels,
+                files: repo.changed_files(&pr.merge_commit)?,
+                category,
+            })
+        })
+        .collect()
+}
+
+/// Writes HTML and Markdown pages for reviewing the given PRs in `.git`.
+///
+/// Returns the URL of the HTML page.
+fn write_review_page(
+    opts: &Options,
+    name: &str,
+    title: &str,
+    items: &[ReviewItem],
+) -> Result<String> {
+    let path = Command::git_args([
+        "rev-parse",
+        "--git-path",
+        &format!("cargo-new-release-review-{}", name),
+    ])
+    .run_stdout()?;
+    let path = env::current_dir()?.join(path);
+    let html_path = path.with_extension("html");
+    let md_path = path.with_extension("md");
+    if opts.dry_run {
+        eprintln!("dry-run: would write {}", md_path.display());
+    } else {
+        fs::write(&html_path, review::html(title, items))
+            .with_context(|| format_err!("failed to write {}", html_path.display()))?;
+        fs::write(&md_path, review::markdown(title, items))
+            .with_context(|| format_err!("failed to write {}", md_path.display()))?;
+        eprintln!("wrote review page {}", md_path.display());
+    }
+    Ok(format!("file://{}", html_path.display()))
+}
+
+/// Waits for the user to update the nightly section of the changelog.
+fn review_nightly(opts: &Options, next_version: &Version, urls: &[String]) -> Result<()> {
+    let urls: Vec<_> = urls.iter().map(String::as_str).collect();
+    open_browser(opts, &urls)?;
 
     eprintln!(
         "Update the nightly version 1.{}.0 and come back when finished.",
         next_version.minor - 1
     );
-    if !Confirm::new()
-        .with_prompt("Ready to continue?")
-        .default(true)
-        .interact()?
-    {
+    if !opts.dry_run && !confirm("nightly-reviewed", "Ready to continue?", true)? {
         exit(1);
     }
+    Ok(())
+}
 
-    let beta_urls: Vec<_> = beta_prs
-        .iter()
-        .map(|(_pr, url, _descr)| url.as_str())
-        .collect();
-    open_browser(&beta_urls)?;
+/// Waits for the user to update the beta section of the changelog.
+fn review_beta(
+    opts: &Options,
+    rust_repo: &str,
+    next_version: &Version,
+    urls: &[String],
+) -> Result<()> {
+    let beta_minor_version = rust_beta_version(opts, rust_repo, next_version)?;
+    let urls: Vec<_> = urls.iter().map(String::as_str).collect();
+    open_browser(opts, &urls)?;
 
     eprintln!(
         "Update the beta version 1.{}.0 and come back when finished.",
         beta_minor_version
     );
-    if !Confirm::new()
-        .with_prompt("Ready to commit?")
-        .default(true)
-        .interact()?
-    {
+    if !opts.dry_run && !confirm("beta-reviewed", "Ready to commit?", true)? {
         exit(1);
     }
 
     Ok(())
 }
 
-fn open_browser(urls: &[&str]) -> Result<()> {
-    if !Command::new("/Applications/Firefox.app/Contents/MacOS/firefox")
-        .arg("-url")
-        .args(urls)
-        .run_success()?
-    {
-        eprintln!("error: failed to open firefox");
-        exit(1);
+fn open_browser(opts: &Options, urls: &[&str]) -> Result<()> {
+    if opts.dry_run {
+        for url in urls {
+            eprintln!("dry-run: would open {}", url);
+        }
+        return Ok(());
     }
-    Ok(())
+    opts.opener.open(urls)
 }
 
-fn find_prs(changelog: &str, start: &str, end: &str) -> Result<Vec<(u32, String, String)>> {
-    let cmd = format!("log --first-parent {}...{}", start, end);
-    let log = Command::git(&cmd).run_stdout()?;
-    let commits = cargo_new_release::commits_in_log(&log);
+fn find_prs(
+    opts: &Options,
+    changelog: &Changelog,
+    start: &str,
+    end: &str,
+) -> Result<Vec<PullRequest>> {
+    let log = git::open(Path::new("."))?.first_parent_log(start, end)?;
+    let commits = cargo_new_release::pull_requests(&log, &opts.repo);
 
     let (dupe, new): (Vec<_>, Vec<_>) = commits
         .into_iter()
-        .partition(|(pr, _url, _descr)| changelog.contains(&format!("[#{}]", pr)));
-    for (pr, _url, _descr) in dupe {
-        eprintln!("skipping PR #{}, already documented", pr);
+        .partition(|pr| changelog.contains_pr(pr.number));
+    for pr in dupe {
+        eprintln!("skipping PR #{}, already documented", pr.number);
     }
     Ok(new)
 }
 
-/// Commits the changelog update.
-fn commit_changelog(next_version: &Version) -> Result<()> {
-    if !Command::git("commit -a -m")
-        .arg(format!("Update changelog for 1.{}", next_version.minor - 2))
-        .run_success()?
+/// Checks the edited changelog for mistakes.
+///
+/// With `--dry-run` nothing was written, so this checks `dry_run_changelog`,
+/// the changelog `prep-changelog` would have written.
+fn check_changelog(opts: &Options, dry_run_changelog: Option<&str>) -> Result<()> {
+    let contents = match dry_run_changelog {
+        _ if !opts.dry_run => read_file(opts, CHANGELOG_PATH)?,
+        Some(contents) => contents.to_string(),
+        None => {
+            eprintln!("dry-run: skipping the changelog lint, as `prep-changelog` did not run");
+            return Ok(());
+        }
+    };
+    let errors = report_lint(CHANGELOG_PATH, &contents, &opts.calendar)?;
+    if errors > 0
+        && !opts.dry_run
+        && !confirm_safety("changelog-lint", "Do you want to continue?", false)?
     {
+        exit(1);
+    }
+    Ok(())
+}
+
+/// Prints the problems found in a changelog, returning the number of
+/// errors.
+fn report_lint(path: &str, contents: &str, calendar: &Calendar) -> Result<usize> {
+    let changelog = Changelog::parse(contents);
+    let repo = git::open(Path::new(".")).ok();
+    let findings = lint::lint(&changelog, calendar, repo.as_deref());
+    for finding in &findings {
+        eprintln!("{}:{}", path, finding);
+    }
+    Ok(findings
+        .iter()
+        .filter(|finding| finding.severity == Severity::Error)
+        .count())
+}
+
+/// Runs `cargo-new-release lint-changelog [--schedule-exceptions PATH] [PATH]`.
+///
+/// The path defaults to the changelog of the repo in the current directory.
+fn lint_changelog_command(args: Vec<String>) -> Result<()> {
+    let mut path = None;
+    let mut schedule_exceptions = None;
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--schedule-exceptions" => {
+                schedule_exceptions = Some(
+                    args.next()
+                        .ok_or_else(|| format_err!("--schedule-exceptions requires a path"))?,
+                );
+            }
+            s if s.starts_with('-') => bail!("unknown option `{}`", s),
+            _ if path.is_none() => path = Some(arg),
+            _ => bail!("unexpected argument `{}`", arg),
+        }
+    }
+    let calendar = Calendar::from_config(schedule_exceptions.as_deref())?;
+    let path = match path {
+        Some(path) => path,
+        None => {
+            goto_root()?;
+            CHANGELOG_PATH.to_string()
+        }
+    };
+    let contents =
+        fs::read_to_string(&path).with_context(|| format_err!("failed to read {}", path))?;
+    match report_lint(&path, &contents, &calendar)? {
+        0 => Ok(()),
+        errors => bail!("{} has {} error(s)", path, errors),
+    }
+}
+
+/// Runs `cargo-new-release changelog-coverage [--version 1.N] START..END`.
+///
+/// Reports how well the changelog covers the PRs merged in the range. The
+/// version defaults to that of an `END` like `rust-1.N.0`.
+fn changelog_coverage_command(args: Vec<String>) -> Result<()> {
+    let mut range = None;
+    let mut version = None;
+    let mut repo = None;
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--version" => {
+                version = Some(
+                    args.next()
+                        .ok_or_else(|| format_err!("--version requires a version like 1.70"))?,
+                );
+            }
+            "--repo" => {
+                repo = Some(
+                    args.next()
+                        .ok_or_else(|| format_err!("--repo requires `owner/name`"))?,
+                );
+            }
+            s if s.starts_with('-') => bail!("unknown option `{}`", s),
+            _ if range.is_none() => range = Some(arg),
+            _ => bail!("unexpected argument `{}`", arg),
+        }
+    }
+    let range =
+        range.ok_or_else(|| format_err!("expected a range like rust-1.70.0..rust-1.71.0"))?;
+    let (start, end) = match range.split_once("..") {
+        Some((start, end)) if !start.is_empty() && !end.is_empty() && !end.starts_with('.') => {
+            (start, end)
+        }
+        _ => bail!(
+            "expected a range like rust-1.70.0..rust-1.71.0, got `{}`",
+            range
+        ),
+    };
+    let version_re = Regex::new(r"^1\.[0-9]+(\.[0-9]+)?$").unwrap();
+    let version = match version {
+        Some(version) => version,
+        None => {
+            let branch = end.rsplit('/').next().unwrap_or_default();
+            let version = branch.strip_prefix("rust-").unwrap_or_default();
+            let version = version.strip_suffix(".0").unwrap_or(version);
+            if !version_re.is_match(version) {
+                bail!(
+                    "cannot tell which version `{}` is, use --version to say",
+                    end
+                );
+            }
+            version.to_string()
+        }
+    };
+    if !version_re.is_match(&version) {
+        bail!("expected a version like 1.70 or 1.66.1, got `{}`", version);
+    }
+    let repo = GitHubRepo::from_config(repo.as_deref())?;
+    goto_root()?;
+
+    let git = git::open(Path::new("."))?;
+    let log = git.first_parent_log(&git::resolve(&*git, start)?, &git::resolve(&*git, end)?)?;
+    let prs = cargo_new_release::pull_requests(&log, &repo);
+    let changelog = Changelog::parse(&fs::read_to_string(CHANGELOG_PATH)?);
+    if !changelog.releases.iter().any(|r| r.is_version(&version)) {
+        eprintln!("warning: {} has no section for {}", CHANGELOG_PATH, version);
+    }
+    let coverage = coverage::coverage(&changelog, &version, &prs);
+
+    println!("{} PRs merged in {} for {}", prs.len(), range, version);
+    if !coverage.missing.is_empty() {
+        println!("\nNot in the changelog:");
+        for pr in &coverage.missing {
+            println!("  #{} {}\n    {}", pr.number, pr.title, pr.url);
+        }
+    }
+    if !coverage.wrong_version.is_empty() {
+        println!("\nDocumented under another version:");
+        for (pr, locations) in &coverage.wrong_version {
+            let locations: Vec<_> = locations
+                .iter()
+                .map(|l| format!("{} ({}:{})", l.release, CHANGELOG_PATH, l.line))
+                .collect();
+            println!(
+                "  #{} {}\n    in {}",
+                pr.number,
+                pr.title,
+                locations.join(", ")
+            );
+        }
+    }
+    if !coverage.extra.is_empty() {
+        println!(
+            "\nIn the changelog for {} but not merged in {}:",
+            version, range
+        );
+        for link in &coverage.extra {
+            println!("  #{} ({}:{})", link.number, CHANGELOG_PATH, link.line);
+        }
+    }
+    if coverage == Coverage::default() {
+        println!("The changelog covers every PR.");
+    }
+    Ok(())
+}
+
+/// Runs `cargo-new-release schedule [--schedule-exceptions PATH] [DATE | 1.N]`.
+///
+/// Shows the version on each channel on a date, defaulting to today, and
+/// when the next branch cut is. Given a version, shows when it is on each
+/// channel instead.
+fn schedule_command(args: Vec<String>) -> Result<()> {
+    let mut when = None;
+    let mut schedule_exceptions = None;
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--schedule-exceptions" => {
+                schedule_exceptions = Some(
+                    args.next()
+                        .ok_or_else(|| format_err!("--schedule-exceptions requires a path"))?,
+                );
+            }
+            s if s.starts_with('-') => bail!("unknown option `{}`", s),
+            _ if when.is_none() => when = Some(arg),
+            _ => bail!("unexpected argument `{}`", arg),
+        }
+    }
+    let calendar = Calendar::from_config(schedule_exceptions.as_deref())?;
+    let date = |minor: u64| -> Result<String> {
+        Ok(calendar.release_date(minor)?.format(calendar::DATE_FORMAT))
+    };
+
+    if let Some(minor) = when.as_deref().and_then(calendar::parse_version) {
+        if let Some(nightly) = minor.checked_sub(2) {
+            println!("1.{} is nightly from {}", minor, date(nightly)?);
+        }
+        if let Some(beta) = minor.checked_sub(1) {
+            println!("1.{} is beta from {}", minor, date(beta)?);
+        }
+        println!("1.{} is released on {}", minor, date(minor)?);
+        return Ok(());
+    }
+    let today = match &when {
+        Some(when) => calendar::parse_date(when)
+            .context("expected a date like 2023-06-01 or a version like 1.70")?,
+        None => calendar::today(),
+    };
+    let channels = calendar
+        .channels_on(today)
+        .ok_or_else(|| format_err!("{} is before Rust 1.0", today.format(calendar::DATE_FORMAT)))?;
+    println!("On {}:", today.format(calendar::DATE_FORMAT));
+    println!(
+        "  stable   1.{} (released {})",
+        channels.stable,
+        date(channels.stable)?
+    );
+    println!(
+        "  beta     1.{} (due {})",
+        channels.beta,
+        date(channels.beta)?
+    );
+    println!(
+        "  nightly  1.{} (due {})",
+        channels.nightly,
+        date(channels.nightly)?
+    );
+    let (next_beta, cut) = calendar.next_branch_cut(today)?;
+    println!(
+        "1.{} branches to beta in {} day(s), on {}",
+        next_beta,
+        calendar.days_until_branch_cut(today)?,
+        cut.format(calendar::DATE_FORMAT)
+    );
+    Ok(())
+}
+
+/// Commits the changelog update.
+fn commit_changelog(opts: &Options, rust_repo: &str, next_version: &Version) -> Result<()> {
+    let beta_minor_version = rust_beta_version(opts, rust_repo, next_version)?;
+    if !run_mutating(
+        opts,
+        &mut Command::git_args([
+            "commit",
+            "-a",
+            "-m",
+            &format!("Update changelog for 1.{}", beta_minor_version),
+        ]),
+    )? {
         eprintln!("error: failed to commit changelog");
         exit(1);
     }
@@ -304,43 +1339,137 @@ fn commit_changelog(next_version: &Version) -> Result<()> {
 }
 
 /// Creates the PR.
-fn create_pr(next_vers: &Version) -> Result<()> {
-    if !Command::git("push").run_success()? {
+fn create_pr(opts: &Options, next_vers: &Version) -> Result<()> {
+    if !run_mutating(opts, &mut Command::git("push"))? {
         eprintln!("error: failed to push");
         exit(1);
     }
     let origin = Command::git("remote get-url origin").run_stdout()?;
-    let user_re = Regex::new(r"([a-zA-Z0-9-]+)/cargo").unwrap();
+    let name = &opts.repo.name;
+    let user_re = Regex::new(&format!(r"([a-zA-Z0-9-]+)/{}", regex::escape(name))).unwrap();
     let user_cap = user_re.captures(&origin).expect("user in origin");
     let username = &user_cap[1];
-    open_browser(&[&format!(
-        "https://github.com/{username}/cargo/pull/new/version-bump"
-    )])?;
+    open_browser(
+        opts,
+        &[&format!(
+            "https://github.com/{username}/{name}/pull/new/version-bump"
+        )],
+    )?;
     // TODO: Use github API (or maybe query-strings?) to set title
-    eprintln!("title:\nBump to {}, update changelog", next_vers);
+    if updates_changelog(next_vers) {
+        eprintln!("title:\nBump to {}, update changelog", next_vers);
+    } else {
+        eprintln!("title:\nBump to {}", next_vers);
+    }
     Ok(())
 }
 
-fn next_version_date(next_vers: &Version) -> String {
-    let first = time::date!(2015 - 05 - 15); // 1.0.0 release date
-    let next_days = ((next_vers.minor - 1) * 42) as i64;
-    let next_date = time::Date::from_julian_day(first.julian_day() + next_days - 1);
-    next_date.format("%Y-%m-%d")
+/// Runs a single step of the release process.
+fn run_step(opts: &Options, progress: &mut Progress, step: Step) -> Result<()> {
+    match step {
+        Step::CheckStatus => check_status(opts),
+        Step::CreateBranch => create_branch(opts),
+        Step::BumpVersion => {
+            progress.next_version = Some(bump_version_toml(opts)?);
+            Ok(())
+        }
+        Step::WaitForInspection => wait_for_inspection(opts, &progress.rust_repo),
+        Step::CommitBump => commit_bump(opts, &progress.next_version(opts)?),
+        Step::PrepChangelog => {
+            let next_vers = progress.next_version(opts)?;
+            prep_changelog(opts, progress, &next_vers)
+        }
+        Step::ReviewNightly => {
+            review_nightly(opts, &progress.next_version(opts)?, &progress.nightly_urls)
+        }
+        Step::ReviewBeta => {
+            let next_vers = progress.next_version(opts)?;
+            review_beta(opts, &progress.rust_repo, &next_vers, &progress.beta_urls)
+        }
+        Step::LintChangelog => check_changelog(opts, progress.dry_run_changelog.as_deref()),
+        Step::CommitChangelog => {
+            let next_vers = progress.next_version(opts)?;
+            commit_changelog(opts, &progress.rust_repo, &next_vers)
+        }
+        Step::CreatePr => create_pr(opts, &progress.next_version(opts)?),
+    }
 }
 
 fn doit() -> Result<()> {
-    let rust_repo = env::args()
-        .skip(1)
-        .next()
-        .ok_or_else(|| format_err!("expected path to rust repo as first argument"))?;
-    check_status()?;
-    create_branch()?;
-    let next_vers = bump_version_toml()?;
-    wait_for_inspection()?;
-    commit_bump(&next_vers)?;
-    prep_changelog(&next_vers, &rust_repo)?;
-    commit_changelog(&next_vers)?;
-    create_pr(&next_vers)?;
+    let mut args = cargo_new_release::parse_verbosity(env::args().skip(1).collect());
+    match args.first().map(String::as_str) {
+        Some("lint-changelog") => return lint_changelog_command(args.split_off(1)),
+        Some("changelog-coverage") => return changelog_coverage_command(args.split_off(1)),
+        Some("schedule") => return schedule_command(args.split_off(1)),
+        _ => {}
+    }
+    let mut opts = Options::from_args(args)?;
+    goto_root()?;
+    let mut progress = match Progress::load()? {
+        Some(saved) if opts.resume || opts.from_step.is_some() => saved,
+        Some(saved) => bail!(
+            "a previous run stopped at step `{}`\n\
+             Use --resume to continue it, or `--from-step check-status` to start over.",
+            saved.next_step.name()
+        ),
+        None if opts.resume => bail!("there is no release in progress to resume"),
+        None => Progress::new(
+            opts.rust_repo
+                .clone()
+                .ok_or_else(|| format_err!("expected path to rust repo as first argument"))?,
+        ),
+    };
+    if let Some(rust_repo) = &opts.rust_repo {
+        progress.rust_repo = rust_repo.clone();
+    }
+    if let Some(step) = opts.from_step {
+        progress.next_step = step;
+    }
+    if opts.base.is_none() {
+        opts.base = Some(default_base(&opts, &progress.rust_repo)?);
+    }
+    let start = Step::ALL
+        .iter()
+        .position(|step| *step == progress.next_step)
+        .unwrap();
+    let mut skipped_changelog = false;
+    for (i, step) in Step::ALL.iter().copied().enumerate().skip(start) {
+        if step.is_changelog() {
+            let next_version = progress.next_version(&opts)?;
+            if !updates_changelog(&next_version) {
+                if !skipped_changelog {
+                    eprintln!(
+                        "info: {} is not a new minor version, skipping the changelog",
+                        next_version
+                    );
+                    skipped_changelog = true;
+                }
+                continue;
+            }
+        }
+        progress.next_step = step;
+        run_step(&opts, &mut progress, step).with_context(|| {
+            if opts.dry_run {
+                format_err!("step `{}` failed", step.name())
+            } else {
+                format_err!(
+                    "step `{}` failed, rerun with --resume to retry it",
+                    step.name()
+                )
+            }
+        })?;
+        // Only record a step once it has finished, so that declining the
+        // first prompt does not leave a release in progress behind.
+        if let Some(&next) = Step::ALL.get(i + 1) {
+            if !opts.dry_run {
+                progress.next_step = next;
+                progress.save()?;
+            }
+        }
+    }
+    if !opts.dry_run {
+        Progress::remove()?;
+    }
     Ok(())
 }
 
@@ -350,5 +1479,6 @@ fn main() {
         for cause in e.chain().skip(1) {
             eprintln!("caused by: {}", cause);
         }
+        exit(1);
     }
 }
@@ -0,0 +1,261 @@
+//! Opening URLs for review.
+//!
+//! The opener is described by a string, taken from the first of these that
+//! is set:
+//!
+//! 1. The `--browser` flag.
+//! 2. The `CARGO_NEW_RELEASE_BROWSER` environment variable.
+//! 3. The `cargo-new-release.browser` git config setting.
+//! 4. The first entry of the `BROWSER` environment variable.
+//!
+//! The string is either `print` to write the URLs to stdout, `file:<path>`
+//! to append them to a file, or a command. The command is split into words
+//! like a shell would, so a path with spaces can be quoted, but nothing is
+//! expanded. URLs are passed to the command as arguments, or substituted for
+//! `%s` if the command contains it. Without
+//! any of these, `open` is used on macOS, `start` on Windows, and `xdg-open`
+//! elsewhere.
+//!
+//! URLs are opened in batches of `cargo-new-release.browser-batch` (default
+//! 20), asking before each batch after the first.
+
+use crate::prompt::confirm;
+use crate::CommandExt;
+use anyhow::{bail, format_err, Context, Result};
+use std::env;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// The default number of URLs to open at once.
+const DEFAULT_BATCH: usize = 20;
+
+#[derive(Clone, Debug, PartialEq)]
+enum Target {
+    /// Runs a command with the URLs.
+    Command {
+        program: String,
+        args: Vec<String>,
+        /// Whether the command only accepts one URL at a time.
+        per_url: bool,
+    },
+    /// Writes the URLs to stdout.
+    Print,
+    /// Appends the URLs to a file.
+    File(PathBuf),
+}
+
+/// Opens URLs in a browser, or records them for later.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Opener {
+    target: Target,
+    batch: usize,
+}
+
+impl Opener {
+    /// Determines the opener from the flag or configuration.
+    pub fn from_config(flag: Option<&str>) -> Result<Opener> {
+        let config = |key: &str| -> Result<String> {
+            Command::git_args(["config", "--default", "", "--get", key]).run_stdout()
+        };
+        let spec = match flag {
+            Some(flag) => flag.to_string(),
+            None => match env::var("CARGO_NEW_RELEASE_BROWSER") {
+                Ok(spec) => spec,
+                Err(_) => config("cargo-new-release.browser")?,
+            },
+        };
+        let spec = if spec.is_empty() {
+            env::var("BROWSER")
+                .ok()
+                .and_then(|b| b.split(':').next().map(String::from))
+                .unwrap_or_default()
+        } else {
+            spec
+        };
+        let mut opener = if spec.is_empty() {
+            Opener::platform_default()
+        } else {
+            Opener::parse(&spec)?
+        };
+        let batch = config("cargo-new-release.browser-batch")?;
+        if !batch.is_empty() {
+            opener.batch = batch.parse().ok().filter(|n| *n > 0).ok_or_else(|| {
+                format_err!(
+                    "cargo-new-release.browser-batch should be a positive number, got `{}`",
+                    batch
+                )
+            })?;
+        }
+        Ok(opener)
+    }
+
+    /// Parses an opener description, see the module docs for the format.
+    pub fn parse(spec: &str) -> Result<Opener> {
+        let target = if spec == "print" {
+            Target::Print
+        } else if let Some(path) = spec.strip_prefix("file:") {
+            Target::File(PathBuf::from(path))
+        } else {
+            let mut parts = split_words(spec)
+                .context("invalid browser command")?
+                .into_iter();
+            let program = parts
+                .next()
+                .ok_or_else(|| format_err!("browser command is empty"))?;
+            let args: Vec<_> = parts.collect();
+            let per_url = args.iter().any(|arg| arg.contains("%s"))
+                || Path::new(&program).file_name().and_then(|n| n.to_str()) == Some("xdg-open");
+            Target::Command {
+                program,
+                args,
+                per_url,
+            }
+        };
+        Ok(Opener {
+            target,
+            batch: DEFAULT_BATCH,
+        })
+    }
+
+    fn platform_default() -> Opener {
+        let (program, args, per_url) = if cfg!(target_os = "macos") {
+            ("open", vec![], false)
+        } else if cfg!(windows) {
+            ("cmd", vec!["/c", "start", ""], true)
+        } else {
+            ("xdg-open", vec![], true)
+        };
+        Opener {
+            target: Target::Command {
+                program: program.to_string(),
+                args: args.into_iter().map(String::from).collect(),
+                per_url,
+            },
+            batch: DEFAULT_BATCH,
+        }
+    }
+
+    /// Opens the given URLs.
+    pub fn open(&self, urls: &[&str]) -> Result<()> {
+        let (program, args, per_url) = match &self.target {
+            Target::Print => {
+                for url in urls {
+                    println!("{}", url);
+                }
+                return Ok(());
+            }
+            Target::File(path) => {
+                let mut file = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .with_context(|| format_err!("failed to open {}", path.display()))?;
+                for url in urls {
+                    writeln!(file, "{}", url)?;
+                }
+                eprintln!("wrote {} URLs to {}", urls.len(), path.display());
+                return Ok(());
+            }
+            Target::Command {
+                program,
+                args,
+                per_url,
+            } => (program, args, *per_url),
+        };
+        for (i, batch) in urls.chunks(self.batch).enumerate() {
+            if i > 0
+                && !confirm(
+                    "open-next-batch",
+                    &format!(
+                        "Opened {} of {} URLs, open the next {}?",
+                        i * self.batch,
+                        urls.len(),
+                        batch.len()
+                    ),
+                    true,
+                )?
+            {
+                for url in &urls[i * self.batch..] {
+                    eprintln!("not opened: {}", url);
+                }
+                break;
+            }
+            let mut cmds = Vec::new();
+            if per_url {
+                for url in batch {
+                    let mut cmd = Command::new(program);
+                    if args.iter().any(|arg| arg.contains("%s")) {
+                        cmd.args(args.iter().map(|arg| arg.replace("%s", url)));
+                    } else {
+                        cmd.args(args).arg(url);
+                    }
+                    cmds.push(cmd);
+                }
+            } else {
+                let mut cmd = Command::new(program);
+                cmd.args(args).args(batch);
+                cmds.push(cmd);
+            }
+            for mut cmd in cmds {
+                if !cmd.run_success()? {
+                    bail!(
+                        "failed to open URLs with `{} {}`",
+                        program,
+                        cmd.display_args()
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Splits a command into words like a POSIX shell.
+///
+/// Single quotes keep everything up to the closing quote, double quotes
+/// keep everything except for `\"` and `\\` escapes, and a backslash
+/// outside of quotes keeps the character after it.
+fn split_words(command: &str) -> Result<Vec<String>> {
+    let unterminated = |quote| format_err!("unterminated {} in `{}`", quote, command);
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut chars = command.chars();
+    while let Some(c) = chars.next() {
+        if c.is_whitespace() {
+            words.extend(word.take());
+            continue;
+        }
+        let word = word.get_or_insert_with(String::new);
+        match c {
+            '\'' => loop {
+                match chars.next().ok_or_else(|| unterminated("`'`"))? {
+                    '\'' => break,
+                    c => word.push(c),
+                }
+            },
+            '"' => loop {
+                match chars.next().ok_or_else(|| unterminated("`\"`"))? {
+                    '"' => break,
+                    '\\' => match chars.next().ok_or_else(|| unterminated("`\"`"))? {
+                        c @ ('"' | '\\') => word.push(c),
+                        c => {
+                            word.push('\\');
+                            word.push(c);
+                        }
+                    },
+                    c => word.push(c),
+                }
+            },
+            '\\' => word.push(
+                chars
+                    .next()
+                    .ok_or_else(|| format_err!("nothing to escape after `\\` in `{}`", command))?,
+            ),
+            c => word.push(c),
+        }
+    }
+    words.extend(word);
+    Ok(words)
+}
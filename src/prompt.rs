@@ -0,0 +1,168 @@
+//! Yes/no prompts, with support for running unattended.
+//!
+//! Every prompt has a short name so that its answer can be given ahead of
+//! time in a prompt policy file. Each line of the file is `name = answer`,
+//! where the answer is `yes`, `no`, or `ask`. Blank lines and lines
+//! starting with `#` are ignored.
+//!
+//! Prompts made with [`confirm_safety`] guard against something that looks
+//! wrong, such as a dirty working tree. `--yes` will not answer those; they
+//! fail unless the policy file explicitly says `yes`.
+
+use anyhow::{bail, format_err, Context, Result};
+use dialoguer::Confirm;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, IsTerminal};
+use std::path::Path;
+use std::sync::Mutex;
+
+/// How prompts without a policy are answered.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InputMode {
+    /// Ask on the terminal.
+    Interactive,
+    /// Never ask, fail if there is no policy for a prompt.
+    NoInput,
+    /// Never ask, answer yes to anything without a policy except safety
+    /// prompts.
+    Yes,
+}
+
+/// The answer configured for a prompt in a policy file.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Answer {
+    Yes,
+    No,
+    Ask,
+}
+
+/// Answers to give to prompts, by prompt name.
+#[derive(Clone, Debug, Default)]
+pub struct PromptPolicy {
+    answers: BTreeMap<String, Answer>,
+}
+
+impl PromptPolicy {
+    /// Loads a policy file.
+    pub fn load(path: &Path) -> Result<PromptPolicy> {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format_err!("failed to read prompt policy {}", path.display()))?;
+        PromptPolicy::parse(&contents)
+            .with_context(|| format_err!("invalid prompt policy {}", path.display()))
+    }
+
+    /// Parses the contents of a policy file.
+    pub fn parse(contents: &str) -> Result<PromptPolicy> {
+        let mut answers = BTreeMap::new();
+        for (i, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (name, answer) = line
+                .split_once('=')
+                .ok_or_else(|| format_err!("line {}: expected `name = answer`", i + 1))?;
+            let answer = match answer.trim() {
+                "yes" => Answer::Yes,
+                "no" => Answer::No,
+                "ask" => Answer::Ask,
+                s => bail!(
+                    "line {}: expected `yes`, `no`, or `ask`, got `{}`",
+                    i + 1,
+                    s
+                ),
+            };
+            answers.insert(name.trim().to_string(), answer);
+        }
+        Ok(PromptPolicy { answers })
+    }
+
+    /// Returns the answer for the given prompt, if there is one.
+    pub fn get(&self, name: &str) -> Option<Answer> {
+        self.answers.get(name).copied()
+    }
+}
+
+struct Settings {
+    mode: InputMode,
+    policy: Option<PromptPolicy>,
+}
+
+static SETTINGS: Mutex<Settings> = Mutex::new(Settings {
+    mode: InputMode::Interactive,
+    policy: None,
+});
+
+/// Sets how prompts without a policy are answered.
+pub fn set_input_mode(mode: InputMode) {
+    SETTINGS.lock().unwrap().mode = mode;
+}
+
+/// Sets the answers to give to specific prompts.
+pub fn set_policy(policy: PromptPolicy) {
+    SETTINGS.lock().unwrap().policy = Some(policy);
+}
+
+/// Asks the user a yes/no question.
+///
+/// `name` identifies the prompt in a policy file.
+pub fn confirm(name: &str, prompt: &str, default: bool) -> Result<bool> {
+    ask(name, prompt, default, false)
+}
+
+/// Asks the user whether to continue despite something looking wrong.
+///
+/// Unlike [`confirm`], this is not answered by `--yes`. When not
+/// interactive, it fails unless the policy says `yes` or `no`.
+pub fn confirm_safety(name: &str, prompt: &str, default: bool) -> Result<bool> {
+    ask(name, prompt, default, true)
+}
+
+fn ask(name: &str, prompt: &str, default: bool, safety: bool) -> Result<bool> {
+    let (mode, answer) = {
+        let settings = SETTINGS.lock().unwrap();
+        let answer = settings.policy.as_ref().and_then(|p| p.get(name));
+        (settings.mode, answer)
+    };
+    match (answer, mode) {
+        (Some(Answer::Yes), _) => {
+            eprintln!("{} yes (prompt policy `{}`)", prompt, name);
+            Ok(true)
+        }
+        (Some(Answer::No), _) => {
+            eprintln!("{} no (prompt policy `{}`)", prompt, name);
+            Ok(false)
+        }
+        (Some(Answer::Ask), _) | (None, InputMode::Interactive) => {
+            if !io::stdin().is_terminal() {
+                bail!(
+                    "cannot ask `{}` without a terminal: {}\n\
+                     Use --yes or --no-input with --prompt-policy to run unattended.",
+                    name,
+                    prompt
+                );
+            }
+            Ok(Confirm::new()
+                .with_prompt(prompt)
+                .default(default)
+                .interact()?)
+        }
+        (None, InputMode::Yes) if safety => bail!(
+            "prompt `{}` needs a human to decide: {}\n\
+             Set `{} = yes` in the prompt policy to continue anyway.",
+            name,
+            prompt,
+            name
+        ),
+        (None, InputMode::Yes) => {
+            eprintln!("{} yes (--yes)", prompt);
+            Ok(true)
+        }
+        (None, InputMode::NoInput) => bail!(
+            "prompt `{}` has no answer in the prompt policy: {}",
+            name,
+            prompt
+        ),
+    }
+}
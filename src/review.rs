@@ -0,0 +1,167 @@
+//! Review pages listing the PRs that go into a changelog section.
+//!
+//! Instead of opening one browser tab per PR, all of them are rendered into
+//! a single self-contained page, as both HTML and Markdown, so they can be
+//! triaged offline.
+
+use crate::category::CATEGORIES;
+use crate::PullRequest;
+use std::fmt::Write;
+
+/// The maximum number of bytes of a PR description to show.
+const EXCERPT_LEN: usize = 600;
+
+/// A PR to review, with details gathered from git and GitHub.
+#[derive(Clone, Debug)]
+pub struct ReviewItem {
+    pub pr: PullRequest,
+    /// The PR labels, or `None` if they could not be fetched.
+    pub labels: Option<Vec<String>>,
+    /// Paths changed by the PR.
+    pub files: Vec<String>,
+    /// The changelog section the PR was sorted into, if any.
+    ///
+    /// This is checked on the page.
+    pub category: Option<&'static str>,
+}
+
+impl ReviewItem {
+    fn excerpt(&self) -> String {
+        let body = self.pr.body.trim();
+        if body.len() <= EXCERPT_LEN {
+            return body.to_string();
+        }
+        let mut end = EXCERPT_LEN;
+        while !body.is_char_boundary(end) {
+            end -= 1;
+        }
+        format!("{}…", &body[..end])
+    }
+}
+
+/// Renders the review page as Markdown.
+pub fn markdown(title: &str, items: &[ReviewItem]) -> String {
+    let mut out = format!("# {}\n\n{} PRs to review.\n", title, items.len());
+    for item in items {
+        let pr = &item.pr;
+        write!(
+            out,
+            "\n## [#{}]({}) {}\n\nby {}",
+            pr.number, pr.url, pr.title, pr.author
+        )
+        .unwrap();
+        if let Some(labels) = &item.labels {
+            write!(out, " · labels: {}", labels.join(", ")).unwrap();
+        }
+        out.push('\n');
+        let excerpt = item.excerpt();
+        if !excerpt.is_empty() {
+            out.push('\n');
+            for line in excerpt.lines() {
+                writeln!(out, "> {}", line).unwrap();
+            }
+        }
+        if !item.files.is_empty() {
+            out.push_str("\nFiles:\n");
+            for file in &item.files {
+                writeln!(out, "- `{}`", file).unwrap();
+            }
+        }
+        out.push('\n');
+        for category in CATEGORIES {
+            let mark = if item.category == Some(category) {
+                'x'
+            } else {
+                ' '
+            };
+            writeln!(out, "- [{}] {}", mark, category).unwrap();
+        }
+    }
+    out
+}
+
+/// Renders the review page as a standalone HTML document.
+pub fn html(title: &str, items: &[ReviewItem]) -> String {
+    let mut out = format!(
+        "<!DOCTYPE html>\n\
+         <html>\n\
+         <head>\n\
+         <meta charset=\"utf-8\">\n\
+         <title>{title}</title>\n\
+         <style>\n\
+         body {{ font-family: sans-serif; max-width: 60em; margin: auto; }}\n\
+         section {{ border-top: 1px solid #ccc; padding: 0.5em 0; }}\n\
+         blockquote {{ color: #555; white-space: pre-wrap; }}\n\
+         .labels span {{ background: #eee; border-radius: 1em; padding: 0 0.5em; }}\n\
+         </style>\n\
+         </head>\n\
+         <body>\n\
+         <h1>{title}</h1>\n\
+         <p>{count} PRs to review.</p>\n",
+        title = escape(title),
+        count = items.len()
+    );
+    for item in items {
+        let pr = &item.pr;
+        write!(
+            out,
+            "<section>\n\
+             <h2><a href=\"{url}\">#{number}</a> {title}</h2>\n\
+             <p>by {author}",
+            url = escape(&pr.url),
+            number = pr.number,
+            title = escape(&pr.title),
+            author = escape(&pr.author),
+        )
+        .unwrap();
+        if let Some(labels) = &item.labels {
+            out.push_str(" <span class=\"labels\">");
+            for label in labels {
+                write!(out, "<span>{}</span> ", escape(label)).unwrap();
+            }
+            out.push_str("</span>");
+        }
+        out.push_str("</p>\n");
+        let excerpt = item.excerpt();
+        if !excerpt.is_empty() {
+            writeln!(out, "<blockquote>{}</blockquote>", escape(&excerpt)).unwrap();
+        }
+        if !item.files.is_empty() {
+            writeln!(
+                out,
+                "<details><summary>{} files changed</summary>\n<ul>",
+                item.files.len()
+            )
+            .unwrap();
+            for file in &item.files {
+                writeln!(out, "<li><code>{}</code></li>", escape(file)).unwrap();
+            }
+            out.push_str("</ul></details>\n");
+        }
+        out.push_str("<p>");
+        for category in CATEGORIES {
+            let checked = if item.category == Some(category) {
+                " checked"
+            } else {
+                ""
+            };
+            write!(
+                out,
+                "<label><input type=\"checkbox\" name=\"pr-{}\" value=\"{category}\"{checked}> {category}</label> ",
+                pr.number
+            )
+            .unwrap();
+        }
+        out.push_str("</p>\n</section>\n");
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+/// Escapes text for inclusion in HTML.
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,90 @@
+//! Reading the rust-lang/rust repo that cargo is released with.
+
+use crate::git::GitBackend;
+use crate::CommandExt;
+use anyhow::{format_err, Context, Result};
+use semver::Version;
+use std::fmt;
+use std::process::Command;
+
+/// The path of the cargo submodule.
+pub const CARGO_PATH: &str = "src/tools/cargo";
+
+/// Why a submodule commit could not be found.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SubmoduleError {
+    /// Nothing exists at the path.
+    Missing { rev: String, path: String },
+    /// The path is a file or directory rather than a gitlink.
+    NotGitlink {
+        rev: String,
+        path: String,
+        /// The object type, such as `blob` or `tree`.
+        kind: String,
+    },
+}
+
+impl fmt::Display for SubmoduleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SubmoduleError::Missing { rev, path } => {
+                write!(f, "`{}` does not exist at {}", path, rev)
+            }
+            SubmoduleError::NotGitlink { rev, path, kind } => {
+                write!(f, "`{}` at {} is a {}, not a submodule", path, rev, kind)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SubmoduleError {}
+
+/// Determines the name of the rust repo's remote for rust-lang/rust.
+///
+/// This is the value of the `--rust-remote` flag if given, otherwise the
+/// `cargo-new-release.rust-remote` git config setting, defaulting to
+/// `upstream`.
+pub fn remote_from_config(flag: Option<&str>) -> Result<String> {
+    match flag {
+        Some(flag) => Ok(flag.to_string()),
+        None => Command::git("config --default upstream --get cargo-new-release.rust-remote")
+            .run_stdout(),
+    }
+}
+
+/// Returns the Rust version in `src/version` at `rev`.
+pub fn version_at(repo: &dyn GitBackend, rev: &str) -> Result<Version> {
+    let contents = repo
+        .show_file(rev, "src/version")
+        .with_context(|| format_err!("failed to read the Rust version at {}", rev))?;
+    let version = contents.trim();
+    Version::parse(version)
+        .map_err(|e| format_err!("invalid Rust version `{}` at {}: {}", version, rev, e))
+}
+
+/// Returns the commit the submodule at `path` points to at `rev`.
+///
+/// Fails with a [`SubmoduleError`] if `path` is missing or isn't a gitlink.
+pub fn submodule_commit(repo: &dyn GitBackend, rev: &str, path: &str) -> Result<String> {
+    let entry = repo
+        .tree_entry(rev, path)
+        .with_context(|| format_err!("failed to read `{}` at {}", path, rev))?
+        .ok_or_else(|| SubmoduleError::Missing {
+            rev: rev.to_string(),
+            path: path.to_string(),
+        })?;
+    if entry.mode != "160000" || entry.kind != "commit" {
+        return Err(SubmoduleError::NotGitlink {
+            rev: rev.to_string(),
+            path: path.to_string(),
+            kind: entry.kind,
+        }
+        .into());
+    }
+    Ok(entry.hash)
+}
+
+/// Returns the cargo commit that the rust repo uses at `rev`.
+pub fn cargo_commit_at(repo: &dyn GitBackend, rev: &str) -> Result<String> {
+    submodule_commit(repo, rev, CARGO_PATH)
+}
@@ -0,0 +1,169 @@
+//! Finding code that depends on the Rust version, to review when bumping.
+//!
+//! Each bump makes another Rust version stable, so checks for an older
+//! version, tests that wait for one, and comments promising to remove
+//! something once it is released may now be out of date. Nightly-only
+//! tests and `nightly_features_allowed` are found too, since a feature
+//! they cover may have been stabilized.
+
+use crate::{log_finish, log_output, log_start, CommandExt};
+use anyhow::{bail, Result};
+use regex::Regex;
+use std::fmt;
+use std::process::Command;
+use std::sync::OnceLock;
+
+/// A line that may need updating.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Gate {
+    pub path: String,
+    pub line: usize,
+    pub kind: Kind,
+    /// What the line depends on.
+    pub message: String,
+}
+
+impl fmt::Display for Gate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.path, self.line, self.message)
+    }
+}
+
+impl Gate {
+    /// Returns whether the line depends on nightly rather than on a
+    /// particular version.
+    pub fn is_nightly(&self) -> bool {
+        matches!(self.kind, Kind::NightlyTest | Kind::NightlyFeatures)
+    }
+}
+
+/// What a line depends on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    /// Only runs on nightly, with the reason given by the first group.
+    NightlyTest,
+    NightlyFeatures,
+    /// Requires the Rust version whose minor version is the first group.
+    MinVersion,
+    /// Mentions the Rust version whose minor version is the first group.
+    Comment,
+}
+
+fn rules() -> &'static [(Kind, Regex)] {
+    static RULES: OnceLock<Vec<(Kind, Regex)>> = OnceLock::new();
+    RULES.get_or_init(|| {
+        [
+            (
+                Kind::NightlyTest,
+                r#"#\[cargo_test\(.*\bnightly\b(?:.*\breason\s*=\s*"([^"]*)")?"#,
+            ),
+            (Kind::NightlyFeatures, r"\bnightly_features_allowed\b"),
+            // Only versions compared against rustc's, since the same syntax
+            // is used for dependency requirements and test data.
+            (Kind::MinVersion, r"#\[cargo_test\([^\]]*>=\s*1\.([0-9]+)\b"),
+            (
+                Kind::MinVersion,
+                r"(?i)\brustc\w*\b.*Version::new\(\s*1\s*,\s*([0-9]+)\s*,",
+            ),
+            (
+                Kind::MinVersion,
+                r#"(?i)\brustc\w*\b.*[<>]=?\s*"?1\.([0-9]+)\b"#,
+            ),
+            (
+                Kind::Comment,
+                r"(?i)//.*\b(?:stable|stabilized|released|remove|delete|drop)\b.*\b1\.([0-9]+)\b",
+            ),
+            (
+                Kind::Comment,
+                r"(?i)//.*\b1\.([0-9]+)(?:\.[0-9]+)? (?:is|becomes|hits|reaches) stable\b",
+            ),
+        ]
+        .into_iter()
+        .map(|(kind, re)| (kind, Regex::new(re).unwrap()))
+        .collect()
+    })
+}
+
+/// Returns the lines of a Rust source file that may need updating now that
+/// Rust `1.{stable_minor}` is stable.
+///
+/// Checks for and mentions of newer versions are left alone, as they are
+/// still needed.
+pub fn find(path: &str, contents: &str, stable_minor: u64) -> Vec<Gate> {
+    contents
+        .lines()
+        .enumerate()
+        .filter_map(|(i, line)| find_in_line(path, i + 1, line, stable_minor))
+        .collect()
+}
+
+/// Searches the Rust files at `rev` in the repo in the current directory,
+/// like [`find`].
+///
+/// A single `git grep` picks out the lines that could match, rather than
+/// reading every file.
+pub fn search(rev: &str, stable_minor: u64) -> Result<Vec<Gate>> {
+    // Every rule matches one of these.
+    let mut cmd = Command::git_args([
+        "grep",
+        "-n",
+        "-z",
+        "-I",
+        "-E",
+        r"nightly|1\.[0-9]|Version::new",
+    ]);
+    cmd.args([rev, "--", "*.rs"]);
+    let start = log_start(&cmd);
+    let output = cmd.output()?;
+    log_finish(start, output.status);
+    log_output("stderr", &output.stderr);
+    // `git grep` exits with 1 when nothing matches.
+    let no_matches = output.status.code() == Some(1) && output.stderr.is_empty();
+    if !output.status.success() && !no_matches {
+        bail!(
+            "failed to run `git {}`: {}",
+            cmd.display_args(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    let prefix = format!("{}:", rev);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut gates = Vec::new();
+    for line in stdout.lines() {
+        let mut parts = line.splitn(3, '\0');
+        let (Some(path), Some(number), Some(line)) = (parts.next(), parts.next(), parts.next())
+        else {
+            bail!("unexpected `git grep` output `{}`", line);
+        };
+        let path = path.strip_prefix(&prefix).unwrap_or(path);
+        let number = number.parse()?;
+        gates.extend(find_in_line(path, number, line, stable_minor));
+    }
+    Ok(gates)
+}
+
+fn find_in_line(path: &str, number: usize, line: &str, stable_minor: u64) -> Option<Gate> {
+    rules().iter().find_map(|(kind, re)| {
+        let caps = re.captures(line)?;
+        let minor = || caps.get(1)?.as_str().parse::<u64>().ok();
+        let message = match kind {
+            Kind::NightlyTest => match caps.get(1) {
+                Some(reason) => format!("test only runs on nightly: {}", reason.as_str()),
+                None => "test only runs on nightly".to_string(),
+            },
+            Kind::NightlyFeatures => "checks `nightly_features_allowed`".to_string(),
+            Kind::MinVersion => minor()
+                .filter(|&minor| minor <= stable_minor)
+                .map(|minor| format!("requires Rust 1.{}, which is stable", minor))?,
+            Kind::Comment => minor()
+                .filter(|&minor| minor <= stable_minor)
+                .map(|minor| format!("mentions Rust 1.{}, which is stable", minor))?,
+        };
+        Some(Gate {
+            path: path.to_string(),
+            line: number,
+            kind: *kind,
+            message,
+        })
+    })
+}
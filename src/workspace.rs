@@ -0,0 +1,534 @@
+//! The packages in the cargo workspace, and editing their manifests.
+//!
+//! Members are published to crates.io along with cargo, so one that changes
+//! needs a new version, and every crate in the workspace that depends on it
+//! should require that version.
+
+use crate::git::GitBackend;
+use anyhow::{bail, format_err, Context, Result};
+use regex::Regex;
+use semver::Version;
+use toml_edit::{DocumentMut, Item, TableLike, Value};
+
+/// A package in the workspace.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Member {
+    pub name: String,
+    /// The directory of the package relative to the root of the repo, empty
+    /// for the root package.
+    pub dir: String,
+    /// The version, or `None` if it isn't given as a plain string or
+    /// inherited from the workspace.
+    pub version: Option<Version>,
+    /// Whether the version is `version.workspace = true`.
+    pub inherits_version: bool,
+    /// Whether the package is published to crates.io.
+    pub publish: bool,
+    /// The names of the packages it depends on, of any kind, sorted.
+    pub dependencies: Vec<String>,
+}
+
+impl Member {
+    /// Returns the path of the package's `Cargo.toml`.
+    pub fn manifest_path(&self) -> String {
+        manifest_path(&self.dir)
+    }
+
+    /// Returns whether `path` is a file of this package.
+    fn contains(&self, path: &str) -> bool {
+        self.dir.is_empty()
+            || path
+                .strip_prefix(&self.dir)
+                .is_some_and(|rest| rest.starts_with('/'))
+    }
+}
+
+/// The packages of a workspace at some revision.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Workspace {
+    /// The members, starting with the root package if there is one.
+    pub members: Vec<Member>,
+}
+
+impl Workspace {
+    /// Loads the workspace whose root manifest is the `Cargo.toml` at the
+    /// top of the repo, as of the given revision.
+    ///
+    /// Member patterns may use `*` and `?` within a path component, as
+    /// most workspaces do.
+    pub fn load(repo: &dyn GitBackend, rev: &str) -> Result<Workspace> {
+        let root = parse(&repo.show_file(rev, "Cargo.toml")?, "Cargo.toml")?;
+        let workspace_version = get(&root, &WORKSPACE_VERSION)
+            .and_then(Item::as_str)
+            .map(Version::parse)
+            .transpose()
+            .context("invalid `workspace.package.version` in Cargo.toml")?;
+        let mut members = Vec::new();
+        if let Some(member) = member(&root, "", &root, workspace_version.as_ref())? {
+            members.push(member);
+        }
+        let patterns = |key| -> Vec<String> {
+            root.get("workspace")
+                .and_then(|ws| ws.get(key))
+                .and_then(Item::as_array)
+                .map(|array| {
+                    array
+                        .iter()
+                        .filter_map(Value::as_str)
+                        .map(|p| p.trim_end_matches('/').to_string())
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+        let (include, exclude) = (patterns("members"), patterns("exclude"));
+        if include.is_empty() {
+            return Ok(Workspace { members });
+        }
+        let mut dirs: Vec<_> = repo
+            .list_files(rev)?
+            .into_iter()
+            .filter_map(|path| Some(path.strip_suffix("/Cargo.toml")?.to_string()))
+            .filter(|dir| include.iter().any(|p| glob_match(p, dir)))
+            .filter(|dir| {
+                !exclude
+                    .iter()
+                    .any(|p| glob_match(p, dir) || dir.starts_with(&format!("{}/", p)))
+            })
+            .collect();
+        dirs.sort();
+        for dir in dirs {
+            let path = manifest_path(&dir);
+            let manifest = parse(&repo.show_file(rev, &path)?, &path)?;
+            members.extend(member(&manifest, &dir, &root, workspace_version.as_ref())?);
+        }
+        Ok(Workspace { members })
+    }
+
+    /// Returns the root package, if the root manifest has one.
+    pub fn root(&self) -> Option<&Member> {
+        self.members.first().filter(|member| member.dir.is_empty())
+    }
+
+    /// Returns the published members other than the root package that have
+    /// changed between `tag` and `rev` but still have the version they had
+    /// at `tag`.
+    ///
+    /// Members that didn't exist at `tag` haven't been published yet, so
+    /// any version will do for them.
+    pub fn unbumped(&self, repo: &dyn GitBackend, tag: &str, rev: &str) -> Result<Vec<&Member>> {
+        let changed = repo.diff_files(tag, rev)?;
+        let mut unbumped = Vec::new();
+        for member in &self.members {
+            if member.dir.is_empty() || !member.publish || member.inherits_version {
+                continue;
+            }
+            let version = match &member.version {
+                Some(version) => version,
+                None => continue,
+            };
+            if !changed.iter().any(|path| member.contains(path)) {
+                continue;
+            }
+            let path = member.manifest_path();
+            if repo.tree_entry(tag, &path)?.is_none() {
+                continue;
+            }
+            let old = parse(&repo.show_file(tag, &path)?, &path)?;
+            if package_version(&old)?.as_ref() == Some(version) {
+                unbumped.push(member);
+            }
+        }
+        Ok(unbumped)
+    }
+}
+
+/// The key of a package's own version.
+const PACKAGE_VERSION: [&str; 2] = ["package", "version"];
+/// The key of the version packages can inherit from the workspace.
+const WORKSPACE_VERSION: [&str; 3] = ["workspace", "package", "version"];
+
+/// Returns the version of the package in a manifest.
+///
+/// For a package with `version.workspace = true`, or a workspace without a
+/// root package, this is `workspace.package.version`.
+pub fn version(manifest: &str) -> Result<Version> {
+    let doc = parse(manifest, "manifest")?;
+    let key = version_key(&doc)?;
+    let version = get(&doc, key)
+        .and_then(Item::as_str)
+        .ok_or_else(|| format_err!("manifest has no `{}` string", key.join(".")))?;
+    Version::parse(version)
+        .with_context(|| format_err!("invalid `{}` `{}`", key.join("."), version))
+}
+
+/// Sets the version that [`version`] returns, keeping the manifest's
+/// formatting.
+pub fn set_version(manifest: &str, version: &Version) -> Result<String> {
+    let mut doc = parse(manifest, "manifest")?;
+    let key = version_key(&doc)?;
+    let item = get_mut(&mut doc, key)
+        .filter(|item| item.is_str())
+        .ok_or_else(|| format_err!("manifest has no `{}` string", key.join(".")))?;
+    set_str(item, &version.to_string());
+    Ok(doc.to_string())
+}
+
+/// Updates the versions of the given workspace packages in `Cargo.lock`,
+/// keeping its formatting.
+///
+/// Dependencies in the lockfile only include the version when there is more
+/// than one package of that name, and those are updated as well.
+pub fn update_lockfile(lock: &str, bumped: &[(String, Version)]) -> Result<String> {
+    let mut doc = parse(lock, "Cargo.lock")?;
+    let packages = doc
+        .get_mut("package")
+        .and_then(Item::as_array_of_tables_mut)
+        .ok_or_else(|| format_err!("Cargo.lock has no packages"))?;
+    let mut renamed = Vec::new();
+    for package in packages.iter_mut() {
+        // Packages from a registry or git have a source, workspace members
+        // don't.
+        if package.contains_key("source") {
+            continue;
+        }
+        let name = match package.get("name").and_then(Item::as_str) {
+            Some(name) => name.to_string(),
+            None => continue,
+        };
+        let version = match bumped.iter().find(|(bumped, _)| *bumped == name) {
+            Some((_, version)) => version,
+            None => continue,
+        };
+        if let Some(item) = package.get_mut("version").filter(|item| item.is_str()) {
+            let old = format!("{} {}", name, item.as_str().unwrap());
+            renamed.push((old, format!("{} {}", name, version)));
+            set_str(item, &version.to_string());
+        }
+    }
+    for package in packages.iter_mut() {
+        let deps = match package.get_mut("dependencies").and_then(Item::as_array_mut) {
+            Some(deps) => deps,
+            None => continue,
+        };
+        for dep in deps.iter_mut() {
+            let new = dep
+                .as_str()
+                .and_then(|dep| renamed.iter().find(|(old, _)| old == dep))
+                .map(|(_, new)| new);
+            if let Some(new) = new {
+                set_str_value(dep, new);
+            }
+        }
+    }
+    Ok(doc.to_string())
+}
+
+/// Updates the version requirements of path dependencies on the given
+/// packages, keeping the manifest's formatting.
+///
+/// This covers the `[dependencies]` tables of a package, including
+/// platform-specific ones, and `[workspace.dependencies]`. Operators such
+/// as `=` are kept.
+pub fn update_requirements(manifest: &str, bumped: &[(String, Version)]) -> Result<String> {
+    let mut doc = parse(manifest, "manifest")?;
+    let mut tables: Vec<&mut dyn TableLike> = Vec::new();
+    for (key, item) in doc.iter_mut() {
+        let item = match item.as_table_like_mut() {
+            Some(table) => table,
+            None => continue,
+        };
+        match key.get() {
+            "dependencies" | "dev-dependencies" | "build-dependencies" => tables.push(item),
+            "workspace" => tables.extend(
+                item.get_mut("dependencies")
+                    .and_then(Item::as_table_like_mut),
+            ),
+            "target" => {
+                for (_, target) in item.iter_mut() {
+                    if let Some(target) = target.as_table_like_mut() {
+                        for (key, deps) in target.iter_mut() {
+                            if key.get().ends_with("dependencies") {
+                                tables.extend(deps.as_table_like_mut());
+                            }
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    let operator = Regex::new(r"^\s*(=|\^|~|>=)?\s*").unwrap();
+    for deps in tables {
+        for (key, dep) in deps.iter_mut() {
+            let dep = match dep.as_table_like_mut() {
+                Some(dep) if dep.contains_key("path") => dep,
+                _ => continue,
+            };
+            let name = dep
+                .get("package")
+                .and_then(Item::as_str)
+                .unwrap_or(key.get())
+                .to_string();
+            let version = match bumped.iter().find(|(bumped, _)| *bumped == name) {
+                Some((_, version)) => version,
+                None => continue,
+            };
+            if let Some(req) = dep.get_mut("version").filter(|req| req.is_str()) {
+                let op = &operator.captures(req.as_str().unwrap()).unwrap()[0];
+                let new = format!("{}{}", op, version);
+                set_str(req, &new);
+            }
+        }
+    }
+    Ok(doc.to_string())
+}
+
+/// Returns how `Cargo.lock` disagrees with the workspace.
+///
+/// This compares the entry of each member against its version and
+/// dependencies, without needing to resolve anything from a registry.
+pub fn lockfile_drift(lock: &str, workspace: &Workspace) -> Result<Vec<String>> {
+    let doc = parse(lock, "Cargo.lock")?;
+    let packages = doc
+        .get("package")
+        .and_then(Item::as_array_of_tables)
+        .ok_or_else(|| format_err!("Cargo.lock has no packages"))?;
+    let mut drift = Vec::new();
+    for member in &workspace.members {
+        let version = match &member.version {
+            Some(version) => version.to_string(),
+            None => continue,
+        };
+        let entry = packages.iter().find(|package| {
+            !package.contains_key("source")
+                && package.get("name").and_then(Item::as_str) == Some(&member.name)
+        });
+        let entry = match entry {
+            Some(entry) => entry,
+            None => {
+                drift.push(format!("`{}` is missing", member.name));
+                continue;
+            }
+        };
+        let locked = entry.get("version").and_then(Item::as_str).unwrap_or("");
+        if locked != version {
+            drift.push(format!(
+                "`{}` is locked at {}, but {} has {}",
+                member.name,
+                locked,
+                member.manifest_path(),
+                version
+            ));
+        }
+        let mut locked_deps: Vec<_> = entry
+            .get("dependencies")
+            .and_then(Item::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_str)
+            .filter_map(|dep| dep.split(' ').next())
+            .collect();
+        locked_deps.sort();
+        locked_deps.dedup();
+        for dep in &member.dependencies {
+            if !locked_deps.contains(&dep.as_str()) {
+                drift.push(format!(
+                    "`{}` depends on `{}`, which is not locked",
+                    member.name, dep
+                ));
+            }
+        }
+        for dep in locked_deps {
+            if !member.dependencies.iter().any(|d| d == dep) {
+                drift.push(format!(
+                    "`{}` is locked with a dependency on `{}`, which {} does not have",
+                    member.name,
+                    dep,
+                    member.manifest_path()
+                ));
+            }
+        }
+    }
+    Ok(drift)
+}
+
+/// Returns which key holds the version of the package in a manifest.
+fn version_key(doc: &DocumentMut) -> Result<&'static [&'static str]> {
+    let package = match doc.get("package") {
+        Some(package) => package,
+        None => return Ok(&WORKSPACE_VERSION),
+    };
+    match package.get("version") {
+        Some(version) if version.is_str() => Ok(&PACKAGE_VERSION),
+        Some(version) if inherits(version) => Ok(&WORKSPACE_VERSION),
+        Some(_) => bail!("`package.version` is not a string"),
+        None => bail!("manifest has no `package.version`"),
+    }
+}
+
+/// Returns whether a key is `key.workspace = true`.
+fn inherits(item: &Item) -> bool {
+    item.get("workspace").and_then(Item::as_bool) == Some(true)
+}
+
+fn get<'a>(doc: &'a DocumentMut, key: &[&str]) -> Option<&'a Item> {
+    key.iter()
+        .try_fold(doc.as_item(), |item, key| item.get(key))
+}
+
+fn get_mut<'a>(doc: &'a mut DocumentMut, key: &[&str]) -> Option<&'a mut Item> {
+    key.iter()
+        .try_fold(doc.as_item_mut(), |item, key| item.get_mut(key))
+}
+
+/// Replaces a string value, keeping the whitespace and comments around it.
+fn set_str(item: &mut Item, new: &str) {
+    if let Some(value) = item.as_value_mut() {
+        set_str_value(value, new);
+    }
+}
+
+/// Replaces a string value, keeping the whitespace and comments around it
+/// and whether it is a literal `'string'`.
+fn set_str_value(value: &mut Value, new: &str) {
+    let literal = match value {
+        Value::String(s) => s
+            .as_repr()
+            .and_then(|repr| repr.as_raw().as_str())
+            .is_some_and(|raw| raw.starts_with('\'')),
+        _ => false,
+    };
+    let mut new_value = match format!("'{}'", new).parse() {
+        Ok(quoted) if literal && !new.contains('\'') => quoted,
+        _ => Value::from(new),
+    };
+    *new_value.decor_mut() = value.decor().clone();
+    *value = new_value;
+}
+
+fn manifest_path(dir: &str) -> String {
+    if dir.is_empty() {
+        "Cargo.toml".to_string()
+    } else {
+        format!("{}/Cargo.toml", dir)
+    }
+}
+
+fn parse(contents: &str, path: &str) -> Result<DocumentMut> {
+    contents
+        .parse()
+        .with_context(|| format_err!("failed to parse {}", path))
+}
+
+/// Reads the `[package]` of a manifest, if it has one.
+///
+/// `root` is the root manifest, which dependencies and the version may be
+/// inherited from.
+fn member(
+    manifest: &DocumentMut,
+    dir: &str,
+    root: &DocumentMut,
+    workspace_version: Option<&Version>,
+) -> Result<Option<Member>> {
+    let package = match manifest.get("package") {
+        Some(package) => package,
+        None => return Ok(None),
+    };
+    let name = package
+        .get("name")
+        .and_then(Item::as_str)
+        .ok_or_else(|| format_err!("{} has no package name", manifest_path(dir)))?;
+    let publish = match package.get("publish") {
+        Some(publish) => publish.as_bool().unwrap_or_else(|| {
+            publish
+                .as_array()
+                .is_none_or(|registries| !registries.is_empty())
+        }),
+        None => true,
+    };
+    let inherits_version = package.get("version").is_some_and(inherits);
+    let version = if inherits_version {
+        workspace_version.cloned()
+    } else {
+        package_version(manifest)
+            .with_context(|| format_err!("invalid version in {}", manifest_path(dir)))?
+    };
+    let workspace_deps = get(root, &["workspace", "dependencies"]);
+    let mut dependencies = Vec::new();
+    for deps in dependency_tables(manifest) {
+        for (key, dep) in deps.iter() {
+            // The `package` key renames a dependency, and may come from the
+            // workspace's entry.
+            let dep = match workspace_deps.and_then(|deps| deps.get(key)) {
+                Some(workspace_dep) if inherits(dep) => workspace_dep,
+                _ => dep,
+            };
+            let package = dep.get("package").and_then(Item::as_str).unwrap_or(key);
+            dependencies.push(package.to_string());
+        }
+    }
+    dependencies.sort();
+    dependencies.dedup();
+    Ok(Some(Member {
+        name: name.to_string(),
+        dir: dir.to_string(),
+        version,
+        inherits_version,
+        publish,
+        dependencies,
+    }))
+}
+
+/// Returns the tables of a package's dependencies, including
+/// platform-specific ones.
+fn dependency_tables(manifest: &DocumentMut) -> Vec<&dyn TableLike> {
+    let mut tables = Vec::new();
+    for (key, item) in manifest.iter() {
+        match key {
+            "dependencies" | "dev-dependencies" | "build-dependencies" => {
+                tables.extend(item.as_table_like())
+            }
+            "target" => {
+                let targets = item.as_table_like().into_iter().flat_map(|t| t.iter());
+                for (_, target) in targets {
+                    let tables_of_target =
+                        target.as_table_like().into_iter().flat_map(|t| t.iter());
+                    for (key, deps) in tables_of_target {
+                        if key.ends_with("dependencies") {
+                            tables.extend(deps.as_table_like());
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    tables
+}
+
+fn package_version(manifest: &DocumentMut) -> Result<Option<Version>> {
+    manifest
+        .get("package")
+        .and_then(|package| package.get("version"))
+        .and_then(Item::as_str)
+        .map(|version| Version::parse(version).map_err(Into::into))
+        .transpose()
+}
+
+/// Matches a path against a pattern where `*` and `?` match within a path
+/// component.
+fn glob_match(pattern: &str, path: &str) -> bool {
+    fn matches(pattern: &[u8], path: &[u8]) -> bool {
+        match (pattern.first(), path.first()) {
+            (None, None) => true,
+            (Some(b'*'), _) => {
+                matches(&pattern[1..], path)
+                    || (path.first().is_some_and(|&c| c != b'/') && matches(pattern, &path[1..]))
+            }
+            (Some(b'?'), Some(&c)) if c != b'/' => matches(&pattern[1..], &path[1..]),
+            (Some(p), Some(c)) if p == c => matches(&pattern[1..], &path[1..]),
+            _ => false,
+        }
+    }
+    matches(pattern.as_bytes(), path.as_bytes())
+}
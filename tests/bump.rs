@@ -0,0 +1,95 @@
+//! Tests for choosing the next version.
+
+use cargo_new_release::bump::{self, Bump};
+use semver::Version;
+
+fn v(s: &str) -> Version {
+    Version::parse(s).unwrap()
+}
+
+#[test]
+fn apply() {
+    let cases = [
+        ("0.70.0", "minor", "0.71.0"),
+        ("0.70.3", "minor", "0.71.0"),
+        ("0.70.0", "patch", "0.70.1"),
+        ("0.70.0", "major", "1.0.0"),
+        ("1.2.3", "major", "2.0.0"),
+        ("1.2.3", "minor", "1.3.0"),
+        ("0.72.0-beta.1", "minor", "0.72.0"),
+        ("0.72.0-beta.1", "patch", "0.72.0"),
+        ("0.72.0-beta.1", "major", "1.0.0"),
+        ("1.0.0-rc.1", "major", "1.0.0"),
+        ("0.70.0", "0.72.0-beta.1", "0.72.0-beta.1"),
+    ];
+    for (current, bump, expected) in cases {
+        let bump = Bump::parse(bump).unwrap();
+        assert_eq!(bump.apply(&v(current)), v(expected), "{} {}", current, bump);
+    }
+    for (bump, is_patch) in [
+        ("patch", true),
+        ("0.70.1", true),
+        ("minor", false),
+        ("major", false),
+        ("0.72.0-beta.1", false),
+    ] {
+        assert_eq!(Bump::parse(bump).unwrap().is_patch(), is_patch, "{}", bump);
+    }
+    assert!(Bump::parse("1.2")
+        .unwrap_err()
+        .to_string()
+        .starts_with("expected `major`, `minor`, `patch`, or a version, got `1.2`"));
+}
+
+#[test]
+fn validate() {
+    let tags: Vec<_> = [
+        "0.69.0",
+        "v0.70.0",
+        "0.70.2",
+        "rust-1.69.0",
+        "0.72.0-beta.1",
+    ]
+    .iter()
+    .map(|s| s.to_string())
+    .collect();
+    let check = |current: &str, next: &str| {
+        bump::validate(&v(current), &v(next), &tags).map_err(|e| e.to_string())
+    };
+    assert_eq!(check("0.71.0", "0.72.0"), Ok(()));
+    assert_eq!(check("0.70.2", "0.70.3"), Ok(()));
+    assert_eq!(check("0.71.0", "0.72.0-beta.2"), Ok(()));
+    assert_eq!(
+        check("0.71.0", "0.71.0"),
+        Err("0.71.0 is not newer than the current version 0.71.0".to_string())
+    );
+    assert_eq!(
+        check("0.69.0", "0.70.0"),
+        Err("0.70.0 has already been released, tag `v0.70.0` exists".to_string())
+    );
+    assert_eq!(
+        check("0.70.0", "0.70.1"),
+        Err(
+            "0.70.1 is older than 0.70.2, which has already been released as tag `0.70.2`"
+                .to_string()
+        )
+    );
+    assert!(check("0.71.0", "0.72.0-alpha.1").is_err());
+}
+
+#[test]
+fn previous_tag() {
+    let tags: Vec<_> = [
+        "0.68.0",
+        "v0.69.1",
+        "0.69.0",
+        "0.70.0-beta.1",
+        "rust-1.69.0",
+    ]
+    .iter()
+    .map(|s| s.to_string())
+    .collect();
+    assert_eq!(bump::previous_tag(&v("0.70.0"), &tags), Some("v0.69.1"));
+    assert_eq!(bump::previous_tag(&v("0.69.0"), &tags), Some("0.68.0"));
+    assert_eq!(bump::previous_tag(&v("0.68.0"), &tags), None);
+}
@@ -0,0 +1,97 @@
+//! Tests for the Rust release schedule.
+
+use cargo_new_release::calendar::{self, Calendar, Channels};
+
+fn date(s: &str) -> time::Date {
+    calendar::parse_date(s).unwrap()
+}
+
+fn format(date: time::Date) -> String {
+    date.format(calendar::DATE_FORMAT)
+}
+
+#[test]
+fn release_date() {
+    let calendar = Calendar::new();
+    assert_eq!(format(calendar.release_date(0).unwrap()), "2015-05-15");
+    assert_eq!(format(calendar.release_date(1).unwrap()), "2015-06-25");
+    assert_eq!(format(calendar.release_date(69).unwrap()), "2023-04-20");
+    assert_eq!(format(calendar.release_date(70).unwrap()), "2023-06-01");
+
+    // The schedule stops at the year 9999.
+    assert_eq!(format(calendar.release_date(69_436).unwrap()), "9999-12-16");
+    for minor in [69_437, 99_999_999_999, u64::MAX] {
+        assert_eq!(
+            calendar.release_date(minor).unwrap_err().to_string(),
+            format!("1.{} is too far in the future to schedule", minor)
+        );
+    }
+    assert_eq!(calendar.stable_on(date("9999-12-31")), Some(69_436));
+    assert!(calendar.next_branch_cut(date("9999-12-31")).is_err());
+}
+
+#[test]
+fn channels() {
+    let calendar = Calendar::new();
+    let on = |s| calendar.channels_on(date(s)).unwrap();
+    let channels = |stable| Channels {
+        stable,
+        beta: stable + 1,
+        nightly: stable + 2,
+    };
+    assert_eq!(on("2023-05-31"), channels(69));
+    assert_eq!(on("2023-06-01"), channels(70));
+    assert_eq!(on("2015-05-15"), channels(0));
+    assert_eq!(calendar.channels_on(date("2015-05-14")), None);
+
+    assert_eq!(
+        calendar.next_branch_cut(date("2023-05-01")).unwrap(),
+        (71, date("2023-06-01"))
+    );
+    assert_eq!(
+        calendar.days_until_branch_cut(date("2023-05-01")).unwrap(),
+        31
+    );
+    assert_eq!(
+        calendar.days_until_branch_cut(date("2023-06-01")).unwrap(),
+        42
+    );
+}
+
+#[test]
+fn exceptions() {
+    let calendar = Calendar::parse(
+        "# Delayed a week\n\
+         1.70 = 2023-06-08\n\
+         \n\
+         1.71.0 = 2023-07-13\n",
+    )
+    .unwrap();
+    assert_eq!(format(calendar.release_date(70).unwrap()), "2023-06-08");
+    assert_eq!(format(calendar.release_date(69).unwrap()), "2023-04-20");
+    assert_eq!(calendar.stable_on(date("2023-06-07")), Some(69));
+    assert_eq!(calendar.stable_on(date("2023-06-08")), Some(70));
+    assert_eq!(
+        calendar.days_until_branch_cut(date("2023-06-01")).unwrap(),
+        7
+    );
+
+    let error = |contents| format!("{:#}", Calendar::parse(contents).unwrap_err());
+    assert_eq!(
+        error("1.70 2023-06-08\n"),
+        "line 1: expected `1.N = YYYY-MM-DD`"
+    );
+    assert_eq!(
+        error("2.0 = 2023-06-08\n"),
+        "line 1: expected a version like 1.70"
+    );
+    assert!(error("1.70 = June 8th\n").starts_with("line 1: invalid date `June 8th`"));
+    assert_eq!(
+        error("1.99999999999 = 2023-06-08\n"),
+        "1.99999999999 on 2023-06-08 is out of order with the releases around it"
+    );
+    assert_eq!(
+        error("1.70 = 2023-07-20\n"),
+        "1.70 on 2023-07-20 is out of order with the releases around it"
+    );
+}
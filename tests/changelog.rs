@@ -0,0 +1,264 @@
+//! Tests for parsing and editing `CHANGELOG.md`.
+
+use cargo_new_release::calendar::Calendar;
+use cargo_new_release::changelog::coverage::{self, Location};
+use cargo_new_release::changelog::lint::{self, Severity};
+use cargo_new_release::changelog::{Changelog, CompareLink, Entry, Release};
+use cargo_new_release::{GitHubRepo, MergeStyle, PullRequest};
+
+const CHANGELOG: &str = "\
+# Changelog
+
+## Cargo 1.70 (2023-06-01)
+[abcd1234...HEAD](https://github.com/rust-lang/cargo/compare/abcd1234...HEAD)
+
+### Added
+
+- Added `cargo foo`.
+  [#110](https://github.com/rust-lang/cargo/pull/110)
+  [#111](https://github.com/rust-lang/cargo/pull/111)
+- Nested list:
+  - one
+  - two [#112](https://github.com/rust-lang/cargo/pull/112)
+
+Some prose in the middle.
+
+### Changed
+
+### Nightly only
+
+## Cargo 1.69.1 (2023-04-27)
+Compare link missing.
+
+## Unreleased
+- Not a list  \t
+-
+  trailing";
+
+#[test]
+fn round_trip() {
+    let changelog = Changelog::parse(CHANGELOG);
+    assert_eq!(changelog.to_string(), CHANGELOG);
+    assert_eq!(changelog.preamble, "# Changelog\n\n");
+    for contents in ["", "\n", "no releases\n", "## Cargo", "### Added\n- x"] {
+        assert_eq!(Changelog::parse(contents).to_string(), contents);
+    }
+}
+
+#[test]
+fn structure() {
+    let changelog = Changelog::parse(CHANGELOG);
+    let titles: Vec<_> = changelog.releases.iter().map(|r| r.title()).collect();
+    assert_eq!(
+        titles,
+        [
+            "Cargo 1.70 (2023-06-01)",
+            "Cargo 1.69.1 (2023-04-27)",
+            "Unreleased"
+        ]
+    );
+
+    let r = &changelog.releases[0];
+    assert_eq!(r.version(), Some("1.70"));
+    assert_eq!(r.minor(), Some(70));
+    assert_eq!(r.date(), Some("2023-06-01"));
+    assert_eq!(r.line, 3);
+    let compare = r.compare.as_ref().unwrap();
+    assert_eq!(compare.start(), "abcd1234");
+    assert_eq!(compare.end(), "HEAD");
+    assert_eq!(compare.line, 4);
+    let sections: Vec<_> = r.sections.iter().map(|s| s.title()).collect();
+    assert_eq!(sections, ["Added", "Changed", "Nightly only"]);
+    let added: Vec<_> = r.section("Added").unwrap().entries().collect();
+    assert_eq!(added.len(), 2);
+    let prs: Vec<_> = added[0].prs().iter().map(|pr| pr.number).collect();
+    assert_eq!(prs, [110, 111]);
+    assert_eq!(added[0].prs()[1].line, 10);
+    assert_eq!(added[1].line, 11);
+    assert!(added[1]
+        .text()
+        .ends_with("  - two [#112](https://github.com/rust-lang/cargo/pull/112)\n"));
+    assert_eq!(r.section("Changed").unwrap().entries().count(), 0);
+
+    let r = &changelog.releases[1];
+    assert_eq!(r.version(), Some("1.69.1"));
+    assert_eq!((r.minor(), r.patch()), (Some(69), Some(1)));
+    assert!(r.compare.is_none());
+
+    let r = &changelog.releases[2];
+    assert_eq!(r.version(), None);
+    assert_eq!(r.date(), None);
+    assert_eq!(r.entries().count(), 2);
+
+    assert!(changelog.contains_pr(112));
+    assert!(!changelog.contains_pr(11));
+}
+
+#[test]
+fn edit() {
+    let mut changelog = Changelog::parse(CHANGELOG);
+    let r = &mut changelog.releases[0];
+    r.compare.as_mut().unwrap().set_end("rust-1.70.0");
+    r.section_mut("Added")
+        .unwrap()
+        .push(Entry::new("Added bar", 113, "https://example.com/113"));
+    r.section_mut("Changed").unwrap().push(Entry::new(
+        "Changed baz",
+        114,
+        "https://example.com/114",
+    ));
+    r.push_intro(Entry::new("Unsorted", 115, "https://example.com/115"));
+    let mut release = Release::new(
+        "1.71",
+        "2023-07-13",
+        CompareLink::new("https://github.com/rust-lang/cargo", "ffff0000", "HEAD"),
+        &["Added", "Fixed"],
+    );
+    release.section_mut("Fixed").unwrap().push(Entry::new(
+        "Fixed qux",
+        116,
+        "https://example.com/116",
+    ));
+    changelog.releases.insert(0, release);
+
+    let text = changelog.to_string();
+    assert!(text.starts_with(
+        "# Changelog\n\
+         \n\
+         ## Cargo 1.71 (2023-07-13)\n\
+         [ffff0000...HEAD](https://github.com/rust-lang/cargo/compare/ffff0000...HEAD)\n\
+         \n\
+         ### Added\n\
+         \n\
+         ### Fixed\n\
+         \n\
+         - Fixed qux \n  [#116](https://example.com/116)\n\
+         \n\
+         ## Cargo 1.70 (2023-06-01)\n\
+         [abcd1234...rust-1.70.0](https://github.com/rust-lang/cargo/compare/abcd1234...rust-1.70.0)\n\
+         \n\
+         - Unsorted \n  [#115](https://example.com/115)\n\
+         \n\
+         ### Added\n"
+    ));
+    assert!(text.contains(
+        "  - two [#112](https://github.com/rust-lang/cargo/pull/112)\n\
+         - Added bar \n  [#113](https://example.com/113)\n\
+         \n\
+         Some prose in the middle.\n\
+         \n\
+         ### Changed\n\
+         \n\
+         - Changed baz \n  [#114](https://example.com/114)\n\
+         \n\
+         ### Nightly only\n"
+    ));
+    // Edits survive a round trip.
+    assert_eq!(Changelog::parse(&text).to_string(), text);
+    assert!(Changelog::parse(&text).contains_pr(116));
+}
+
+#[test]
+fn lint() {
+    let contents = "\
+# Changelog
+
+## Cargo 1.69 (2023-04-20)
+[bbbb2222...HEAD](https://github.com/rust-lang/cargo/compare/bbbb2222...HEAD)
+
+### Added
+
+- One.
+  [#101](https://github.com/rust-lang/cargo/pull/101)
+- Two.
+  [#102](https://github.com/rust-lang/cargo/pull/120)
+
+### Fixed
+
+## Cargo 1.70 (2023-06-02)
+[aaaa1111...rust-1.69.0](https://github.com/rust-lang/cargo/compare/aaaa1111...rust-1.69.0)
+
+### Added
+
+- Again.
+  [#101](https://github.com/rust-lang/cargo/pull/101)
+";
+    let findings = lint::lint(&Changelog::parse(contents), &Calendar::new(), None);
+    let findings: Vec<_> = findings.iter().map(|f| f.to_string()).collect();
+    assert_eq!(
+        findings,
+        [
+            "4: error: compare link of 1.69 starts at `bbbb2222`, but 1.70 (line 16) ends at `rust-1.69.0`",
+            "11: error: link for #102 points to #120: https://github.com/rust-lang/cargo/pull/120",
+            "13: warning: section `Fixed` of 1.69 is empty",
+            "15: error: 1.70 is listed after 1.69 (line 3), versions should be newest first",
+            "15: error: 1.70 is dated 2023-06-02, but is scheduled for 2023-06-01",
+            "16: error: compare link of 1.70 ends at `rust-1.69.0`, expected `rust-1.70.0`",
+            "21: error: #101 is already referenced on line 9",
+        ]
+    );
+    let errors = lint::lint(&Changelog::parse(contents), &Calendar::new(), None)
+        .into_iter()
+        .filter(|f| f.severity == Severity::Error)
+        .count();
+    assert_eq!(errors, 6);
+}
+
+#[test]
+fn coverage() {
+    let changelog = Changelog::parse(
+        "\
+## Cargo 1.70 (2023-06-01)
+
+### Added
+
+- New.
+  [#104](https://github.com/rust-lang/cargo/pull/104)
+- Not merged for 1.70.
+  [#99](https://github.com/rust-lang/cargo/pull/99)
+
+## Cargo 1.69 (2023-04-20)
+
+### Fixed
+
+- Backported.
+  [#105](https://github.com/rust-lang/cargo/pull/105)
+",
+    );
+    let repo = GitHubRepo::parse("rust-lang/cargo").unwrap();
+    let pr = |number: u32| PullRequest {
+        number,
+        url: repo.pr_url(number),
+        title: format!("PR {}", number),
+        merge_commit: String::new(),
+        author: String::new(),
+        merge_style: MergeStyle::Squash,
+        body: String::new(),
+    };
+    let prs = [pr(103), pr(104), pr(105)];
+    let cov = coverage::coverage(&changelog, "1.70", &prs);
+    assert_eq!(cov.missing, [pr(103)]);
+    assert_eq!(cov.wrong_version.len(), 1);
+    assert_eq!(cov.wrong_version[0].0, pr(105));
+    assert_eq!(
+        cov.wrong_version[0].1,
+        [Location {
+            release: "Cargo 1.69 (2023-04-20)".to_string(),
+            line: 15
+        }]
+    );
+    let extra: Vec<_> = cov
+        .extra
+        .iter()
+        .map(|link| (link.number, link.line))
+        .collect();
+    assert_eq!(extra, [(99, 8)]);
+
+    // `1.70.0` is the same release.
+    assert_eq!(coverage::coverage(&changelog, "1.70.0", &prs), cov);
+    // Nothing is under a version that isn't in the changelog.
+    let cov = coverage::coverage(&changelog, "1.71", &prs);
+    assert_eq!(cov.missing, [pr(103)]);
+    assert_eq!(cov.wrong_version.len(), 2);
+    assert!(cov.extra.is_empty());
+}
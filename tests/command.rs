@@ -0,0 +1,76 @@
+//! Tests for running and displaying commands.
+
+use cargo_new_release::{CommandExt, MAX_STDERR_LEN};
+use std::process::Command;
+
+fn display(arg: &str) -> String {
+    Command::git_args([arg]).display_args()
+}
+
+#[test]
+fn display_args() {
+    assert_eq!(
+        Command::git_args(["commit", "-m", "Bump to 0.71.0"]).display_args(),
+        "commit -m 'Bump to 0.71.0'"
+    );
+    for safe in [
+        "upstream/rust-1.70.0",
+        "--format=%H",
+        "user@host:a,b",
+        "c++",
+    ] {
+        assert_eq!(display(safe), safe);
+    }
+    assert_eq!(display(""), "''");
+    assert_eq!(display("it's"), r"'it'\''s'");
+    // Brace and tilde expansion, and zsh's `EXTENDED_GLOB`.
+    assert_eq!(display("a{b,c}"), "'a{b,c}'");
+    assert_eq!(display("~"), "'~'");
+    assert_eq!(display("~/cargo"), "'~/cargo'");
+    assert_eq!(display("HEAD^"), "'HEAD^'");
+    assert_eq!(display("*.rs"), "'*.rs'");
+    assert_eq!(display("$HOME"), "'$HOME'");
+}
+
+/// Runs `sh` with the given stderr and exit status.
+fn fail(stderr: &str) -> anyhow::Error {
+    Command::new("sh")
+        .args(["-c", r#"printf %s "$STDERR" >&2; exit 3"#])
+        .env("STDERR", stderr)
+        .run_output()
+        .unwrap_err()
+}
+
+#[test]
+fn run_output_error() {
+    let err = fail("fatal: not a git repository\n");
+    let chain: Vec<_> = err.chain().map(|e| e.to_string()).collect();
+    assert_eq!(chain.len(), 2);
+    assert!(chain[0]
+        .starts_with(r#"failed to run `sh -c 'printf %s "$STDERR" >&2; exit 3'`: exit status"#));
+    assert_eq!(chain[1], "fatal: not a git repository");
+
+    // Without any stderr there is nothing to add.
+    assert_eq!(fail("").chain().count(), 1);
+}
+
+#[test]
+fn run_output_truncates_stderr() {
+    // Two-byte characters, so that the cut falls inside one.
+    let stderr = format!("{}fatal: bad object", "é".repeat(MAX_STDERR_LEN));
+    let err = fail(&stderr);
+    let cause = err.chain().nth(1).unwrap().to_string();
+    let omitted = stderr.len() - MAX_STDERR_LEN + 1;
+    assert_eq!(
+        cause,
+        format!(
+            "... ({} earlier bytes omitted)\n{}",
+            omitted,
+            &stderr[omitted..]
+        )
+    );
+    assert!(cause.ends_with("éfatal: bad object"));
+
+    let fits = "e".repeat(MAX_STDERR_LEN);
+    assert_eq!(fail(&fits).chain().nth(1).unwrap().to_string(), fits);
+}
@@ -0,0 +1,38 @@
+//! Tests that the libgit2 backend agrees with the git CLI.
+
+#![cfg(all(unix, feature = "git2"))]
+
+mod testsupport;
+
+use cargo_new_release::git::{CliBackend, Git2Backend, GitBackend};
+use testsupport::ReleaseFixture;
+
+#[test]
+fn backends_agree() {
+    let rf = ReleaseFixture::new("backends_agree");
+    let cli = CliBackend::new(&rf.cargo);
+    let git2 = Git2Backend::open(&rf.cargo).unwrap();
+    let backends: [&dyn GitBackend; 2] = [&cli, &git2];
+
+    let start = &rf.history.start_1_69;
+    let master = &rf.history.master;
+    for backend in backends {
+        let log = backend.first_parent_log(start, master).unwrap();
+        assert_eq!(log, cli.first_parent_log(start, master).unwrap());
+        assert_eq!(log.first().unwrap().hash, *master);
+        assert!(log.iter().all(|commit| commit.hash != *start));
+        // Commits only reachable from `start` are left out.
+        assert!(backend.first_parent_log(master, start).unwrap().is_empty());
+        assert!(!backend.is_dirty().unwrap());
+    }
+
+    // Untracked files do not count.
+    rf.fixture.write(&rf.cargo.join("untracked.txt"), "new\n");
+    for backend in backends {
+        assert!(!backend.is_dirty().unwrap());
+    }
+    rf.fixture.write(&rf.cargo.join("src/lib.rs"), "// wip\n");
+    for backend in backends {
+        assert!(backend.is_dirty().unwrap());
+    }
+}
@@ -0,0 +1,135 @@
+//! End-to-end tests for `milestone` against a stub GitHub API.
+
+#![cfg(unix)]
+
+mod testsupport;
+
+use testsupport::github::{GitHubStub, State};
+use testsupport::{run, stderr, ReleaseFixture};
+
+#[test]
+fn milestones_prs() {
+    let rf = ReleaseFixture::new("milestones_prs");
+    rf.checkout_rust_cargo();
+    let mut state = State::default();
+    // Enough milestones that the existing 1.69.0 is on the second page.
+    for i in 0..120 {
+        state.add_milestone(&format!("0.{}.0", i));
+    }
+    let m_1_68 = state.add_milestone("1.68.0");
+    let m_1_69 = state.add_milestone("1.69.0");
+    state.issues.insert(101, Some(m_1_69));
+    state.issues.insert(102, Some(m_1_68));
+    for pr in [103, 104, 105] {
+        state.issues.insert(pr, None);
+    }
+    let github = GitHubStub::start(state);
+
+    let output = run(rf.milestone(&github).arg("--yes"), true);
+    let stderr = stderr(&output);
+    assert!(stderr.contains("skipping PR 101, already milestoned to 1.69.0"));
+    assert!(stderr.contains(
+        "PR 102 is already milestoned, but milestone \"1.68.0\" does not match version \"1.69.0\""
+    ));
+
+    let state = github.state.lock().unwrap();
+    assert_eq!(state.milestone_of(101).as_deref(), Some("1.69.0"));
+    assert_eq!(state.milestone_of(102).as_deref(), Some("1.68.0"));
+    assert_eq!(state.milestone_of(103).as_deref(), Some("1.69.0"));
+    assert_eq!(state.milestone_of(104).as_deref(), Some("1.70.0"));
+    assert_eq!(state.milestone_of(105).as_deref(), Some("1.70.0"));
+    // Only 1.70.0 is new.
+    assert_eq!(state.milestones.len(), 123);
+    assert!(state
+        .requests
+        .iter()
+        .any(|r| r == "GET /repos/rust-lang/cargo/milestones"));
+}
+
+#[test]
+fn milestone_declined() {
+    let rf = ReleaseFixture::new("milestone_declined");
+    rf.checkout_rust_cargo();
+    let mut state = State::default();
+    for pr in 101..=105 {
+        state.issues.insert(pr, None);
+    }
+    let github = GitHubStub::start(state);
+
+    let policy = rf.fixture.prompt_policy("milestone = no\n");
+    run(
+        rf.milestone(&github).arg("--prompt-policy").arg(&policy),
+        false,
+    );
+    let state = github.state.lock().unwrap();
+    assert!(state.milestones.is_empty());
+    assert!(state.requests.iter().all(|r| r.starts_with("GET ")));
+}
+
+#[test]
+fn milestone_api_error() {
+    let rf = ReleaseFixture::new("milestone_api_error");
+    rf.checkout_rust_cargo();
+    // PRs unknown to the API give a 404.
+    let github = GitHubStub::start(State::default());
+
+    let output = run(&mut rf.milestone(&github), false);
+    assert!(stderr(&output).contains("failed status 404"));
+}
+
+#[test]
+fn milestone_rust_remote() {
+    let rf = ReleaseFixture::new("milestone_rust_remote");
+    rf.checkout_rust_cargo();
+    rf.fixture
+        .git(&rf.rust, &["remote", "rename", "upstream", "rust-lang"]);
+    let mut state = State::default();
+    for pr in 101..=105 {
+        state.issues.insert(pr, None);
+    }
+    let github = GitHubStub::start(state);
+
+    run(
+        rf.milestone(&github)
+            .args(["--rust-remote", "rust-lang", "--yes"]),
+        true,
+    );
+    let state = github.state.lock().unwrap();
+    assert_eq!(state.milestone_of(101).as_deref(), Some("1.69.0"));
+    assert_eq!(state.milestone_of(105).as_deref(), Some("1.70.0"));
+}
+
+#[test]
+fn milestone_cargo_reverted() {
+    let rf = ReleaseFixture::new("milestone_cargo_reverted");
+    rf.checkout_rust_cargo();
+    // Move cargo back to before #105.
+    let rust_upstream = rf.fixture.path("rust-upstream");
+    let previous = &rf.history.all[rf.history.all.len() - 2];
+    rf.fixture.git(
+        &rust_upstream,
+        &[
+            "update-index",
+            "--cacheinfo",
+            &format!("160000,{},src/tools/cargo", previous),
+        ],
+    );
+    rf.fixture.git(
+        &rust_upstream,
+        &["commit", "-q", "-m", "Revert cargo update"],
+    );
+    let mut state = State::default();
+    for pr in 101..=105 {
+        state.issues.insert(pr, None);
+    }
+    let github = GitHubStub::start(state);
+
+    let output = run(rf.milestone(&github).arg("--yes"), true);
+    assert!(stderr(&output).contains(&format!(
+        "warning: no new PRs between cargo {} and {} in ",
+        &rf.history.master[..8],
+        &previous[..8]
+    )));
+    let state = github.state.lock().unwrap();
+    assert_eq!(state.milestone_of(104).as_deref(), Some("1.70.0"));
+}
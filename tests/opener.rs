@@ -0,0 +1,34 @@
+//! Tests for parsing how URLs are opened.
+
+use cargo_new_release::opener::Opener;
+
+fn parse(spec: &str) -> Opener {
+    Opener::parse(spec).unwrap()
+}
+
+#[test]
+fn parse_quoted_command() {
+    let spaced = parse("'/opt/my browser/firefox' --new-tab");
+    assert_eq!(parse(r#""/opt/my browser/firefox" --new-tab"#), spaced);
+    assert_eq!(parse(r"/opt/my\ browser/firefox   --new-tab"), spaced);
+    assert_ne!(parse("/opt/my browser/firefox --new-tab"), spaced);
+    assert_eq!(
+        parse(r#"firefox "--profile=it's \"mine\"" %s"#),
+        parse(r#"firefox '--profile=it'\''s "mine"' %s"#)
+    );
+
+    let error = |spec| format!("{:#}", Opener::parse(spec).unwrap_err());
+    assert_eq!(
+        error("'/opt/my browser/firefox"),
+        "invalid browser command: unterminated `'` in `'/opt/my browser/firefox`"
+    );
+    assert_eq!(
+        error(r#"firefox "%s"#),
+        r#"invalid browser command: unterminated `"` in `firefox "%s`"#
+    );
+    assert_eq!(
+        error(r"firefox \"),
+        r"invalid browser command: nothing to escape after `\` in `firefox \`"
+    );
+    assert_eq!(error("  "), "browser command is empty");
+}
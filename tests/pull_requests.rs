@@ -0,0 +1,69 @@
+//! Tests for finding PRs in the git log.
+
+use cargo_new_release::git::Commit;
+use cargo_new_release::{pull_requests, GitHubRepo, MergeStyle};
+
+fn commit(hash: &str, author: &str, message: &str) -> Commit {
+    Commit {
+        hash: hash.to_string(),
+        author: author.to_string(),
+        message: message.to_string(),
+    }
+}
+
+#[test]
+fn merge_styles() {
+    let repo = GitHubRepo::parse("rust-lang/cargo").unwrap();
+    let commits = [
+        commit(
+            "a1",
+            "bors",
+            "Auto merge of #101 - ehuss:fix-foo, r=weihanglo\n\nFix foo\n\nFixes #100.\n",
+        ),
+        commit(
+            "a2",
+            "bors",
+            "Auto merge of #102 - r=ehuss\n\nRollup of 2 pull requests\n",
+        ),
+        commit(
+            "b1",
+            "Eric Huss",
+            "Merge pull request #103 from ehuss/fix-bar\n\nFix bar\n",
+        ),
+        commit("b2", "Eric Huss", "Merge pull request #104\n\nFix baz\n"),
+        commit("c1", "Weihang Lo", "Fix qux (#105)\n\nDetails.\n"),
+        commit("d1", "Eric Huss", "Update README\n"),
+    ];
+    let prs = pull_requests(&commits, &repo);
+    let found: Vec<_> = prs
+        .iter()
+        .map(|pr| {
+            (
+                pr.number,
+                pr.title.as_str(),
+                pr.author.as_str(),
+                pr.merge_style,
+                pr.merge_commit.as_str(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        found,
+        [
+            (101, "Fix foo", "ehuss", MergeStyle::Bors, "a1"),
+            (
+                102,
+                "Rollup of 2 pull requests",
+                "bors",
+                MergeStyle::Bors,
+                "a2"
+            ),
+            (103, "Fix bar", "ehuss", MergeStyle::MergeCommit, "b1"),
+            (104, "Fix baz", "Eric Huss", MergeStyle::MergeCommit, "b2"),
+            (105, "Fix qux", "Weihang Lo", MergeStyle::Squash, "c1"),
+        ]
+    );
+    assert_eq!(prs[0].body, "Fixes #100.");
+    assert_eq!(prs[0].url, "https://github.com/rust-lang/cargo/pull/101");
+    assert_eq!(prs[4].body, "Details.");
+}
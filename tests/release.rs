@@ -0,0 +1,845 @@
+//! End-to-end tests for `cargo-new-release`.
+
+#![cfg(unix)]
+
+mod testsupport;
+
+use std::path::Path;
+use testsupport::github::{GitHubStub, State};
+use testsupport::{run, stderr, ReleaseFixture, CHANGELOG_PATH};
+
+#[test]
+fn full_release() {
+    let rf = ReleaseFixture::new("full_release");
+    run(rf.new_release().arg("--yes").arg(&rf.rust), true);
+
+    assert_eq!(
+        rf.git(&["rev-parse", "--abbrev-ref", "HEAD"]),
+        "version-bump"
+    );
+    assert_eq!(
+        rf.git(&["log", "--format=%s", "-3"]),
+        "Update changelog for 1.69\nBump to 0.71.0\nImprove docs (#105)"
+    );
+    assert!(rf.read("Cargo.toml").contains("\nversion = \"0.71.0\"\n"));
+
+    let changelog = rf.read(CHANGELOG_PATH);
+    let beta = &rf.history.beta[..8];
+    let start = &rf.history.start_1_69[..8];
+    assert!(changelog.starts_with(&format!(
+        "# Changelog\n\
+         \n\
+         ## Cargo 1.70 (2023-06-01)\n\
+         [{beta}...HEAD](https://github.com/rust-lang/cargo/compare/{beta}...HEAD)\n\
+         \n\
+         ### Added\n\
+         \n\
+         ### Changed\n\
+         \n\
+         - Improve docs \n  [#105](https://github.com/rust-lang/cargo/pull/105)\n\
+         \n\
+         ### Fixed\n\
+         \n\
+         ### Nightly only\n\
+         \n\
+         - Nightly feature \n  [#104](https://github.com/rust-lang/cargo/pull/104)\n\
+         \n\
+         ## Cargo 1.69 (2023-04-20)\n\
+         [{start}...rust-1.69.0](https://github.com/rust-lang/cargo/compare/{start}...rust-1.69.0)\n\
+         \n\
+         - Update changelog \n  [#102](https://github.com/rust-lang/cargo/pull/102)\n\
+         \n\
+         ### Added\n\
+         \n\
+         - Added feature A.\n  [#101](https://github.com/rust-lang/cargo/pull/101)\n\
+         \n\
+         ### Changed\n\
+         \n\
+         ### Fixed\n\
+         \n\
+         - Fix bug B \n  [#103](https://github.com/rust-lang/cargo/pull/103)\n\
+         \n\
+         ### Nightly only\n\
+         \n\
+         ## Cargo 1.68 (2023-03-09)\n"
+    )));
+
+    assert_eq!(
+        rf.fixture
+            .git(&rf.origin, &["log", "--format=%s", "-1", "version-bump"]),
+        "Update changelog for 1.69"
+    );
+    let opened = rf.fixture.opened_urls();
+    assert_eq!(opened.len(), 3);
+    assert!(opened[0].starts_with("file://"));
+    assert!(opened[0].ends_with("/.git/cargo-new-release-review-nightly.html"));
+    assert!(opened[1].ends_with("/.git/cargo-new-release-review-beta.html"));
+    assert_eq!(
+        opened[2],
+        "https://github.com/tester/cargo/pull/new/version-bump"
+    );
+    assert!(!rf
+        .cargo
+        .join(".git/cargo-new-release-progress.json")
+        .exists());
+}
+
+#[test]
+fn dry_run_changes_nothing() {
+    let rf = ReleaseFixture::new("dry_run_changes_nothing");
+    let output = run(rf.new_release().arg("--dry-run").arg(&rf.rust), true);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = stderr(&output);
+
+    assert!(stderr.contains("dry-run: would run `git checkout -B version-bump upstream/master`"));
+    assert!(stdout.contains("-version = \"0.70.0\"\n+version = \"0.71.0\""));
+    assert!(stderr.contains("dry-run: would run `git commit -a -m 'Bump to 0.71.0'`"));
+    assert!(stdout.contains("+- Nightly feature \n+  [#104]"));
+    assert!(stderr.contains("dry-run: would write "));
+    assert!(stderr.contains("/.git/cargo-new-release-review-nightly.html"));
+    // The lint checks the changelog that would have been written.
+    assert!(stderr.contains(&format!(
+        "{}:6: warning: section `Added` of 1.70 is empty",
+        CHANGELOG_PATH
+    )));
+    assert!(stderr.contains("dry-run: would run `git push`"));
+
+    assert_eq!(rf.git(&["rev-parse", "--abbrev-ref", "HEAD"]), "master");
+    assert_eq!(rf.git(&["rev-parse", "HEAD"]), rf.history.master);
+    assert_eq!(rf.git(&["status", "--porcelain"]), "");
+    assert!(rf.fixture.opened_urls().is_empty());
+    assert!(!rf
+        .cargo
+        .join(".git/cargo-new-release-progress.json")
+        .exists());
+
+    let output = run(
+        rf.new_release()
+            .args(["--dry-run", "--from-step", "lint-changelog"])
+            .arg(&rf.rust),
+        true,
+    );
+    let err = testsupport::stderr(&output);
+    assert!(err.contains("dry-run: skipping the changelog lint, as `prep-changelog` did not run"));
+    assert!(err.contains("dry-run: would run `git commit -a -m 'Update changelog for 1.69'`"));
+    assert!(!rf
+        .cargo
+        .join(".git/cargo-new-release-progress.json")
+        .exists());
+}
+
+#[test]
+fn resume_after_abort() {
+    let rf = ReleaseFixture::new("resume_after_abort");
+    // Decline at the nightly review prompt.
+    let policy = rf.fixture.prompt_policy("nightly-reviewed = no\n");
+    run(
+        rf.new_release()
+            .arg("--yes")
+            .arg("--prompt-policy")
+            .arg(&policy)
+            .arg(&rf.rust),
+        false,
+    );
+    let progress = rf.read(".git/cargo-new-release-progress.json");
+    assert!(progress.contains("\"next_step\": \"review-nightly\""));
+    // Simulate editing the changelog while reviewing.
+    let changelog = rf
+        .read(CHANGELOG_PATH)
+        .replace("Nightly feature", "Edited entry");
+    std::fs::write(rf.cargo.join(CHANGELOG_PATH), changelog).unwrap();
+
+    // A new run refuses to clobber the one in progress.
+    let output = run(rf.new_release().arg(&rf.rust), false);
+    assert!(stderr(&output).contains("a previous run stopped at step `review-nightly`"));
+
+    run(rf.new_release().args(["--resume", "--yes"]), true);
+    assert_eq!(
+        rf.git(&["log", "--format=%s", "-2"]),
+        "Update changelog for 1.69\nBump to 0.71.0"
+    );
+    assert!(rf.read(CHANGELOG_PATH).contains("- Edited entry \n"));
+    assert!(!rf
+        .cargo
+        .join(".git/cargo-new-release-progress.json")
+        .exists());
+}
+
+#[test]
+fn rerun_prep_changelog() {
+    let rf = ReleaseFixture::new("rerun_prep_changelog");
+    let policy = rf.fixture.prompt_policy("nightly-reviewed = no\n");
+    run(
+        rf.new_release()
+            .arg("--yes")
+            .arg("--prompt-policy")
+            .arg(&policy)
+            .arg(&rf.rust),
+        false,
+    );
+    let changelog = rf.read(CHANGELOG_PATH);
+
+    let output = run(
+        rf.new_release()
+            .args(["--from-step", "prep-changelog", "--yes"]),
+        true,
+    );
+    assert!(stderr(&output).contains(&format!(
+        "info: {} already has a section for 1.70, not preparing it again",
+        CHANGELOG_PATH
+    )));
+    let committed = rf
+        .fixture
+        .git(&rf.cargo, &["show", &format!("HEAD:{}", CHANGELOG_PATH)]);
+    assert_eq!(committed.trim_end(), changelog.trim_end());
+    assert_eq!(committed.matches("## Cargo 1.70").count(), 1);
+    assert_eq!(
+        rf.fixture.opened_urls().last().unwrap(),
+        "https://github.com/tester/cargo/pull/new/version-bump"
+    );
+}
+
+#[test]
+fn dirty_tree_declined() {
+    let rf = ReleaseFixture::new("dirty_tree_declined");
+    std::fs::write(rf.cargo.join("src/lib.rs"), "// wip\n").unwrap();
+    let policy = rf.fixture.prompt_policy("dirty-tree = no\n");
+    let output = run(
+        rf.new_release()
+            .arg("--prompt-policy")
+            .arg(&policy)
+            .arg(&rf.rust),
+        false,
+    );
+    let err = stderr(&output);
+    assert!(err.contains("Working tree has changes."));
+    assert!(err.contains("Do you want to continue? no (prompt policy `dirty-tree`)"));
+    // Nothing ran, so there is no release in progress.
+    assert!(!rf
+        .cargo
+        .join(".git/cargo-new-release-progress.json")
+        .exists());
+    run(rf.new_release().arg("--resume"), false);
+    assert_eq!(rf.git(&["rev-parse", "--abbrev-ref", "HEAD"]), "master");
+    assert!(Path::new(&rf.cargo.join("src/lib.rs")).exists());
+}
+
+#[test]
+fn unattended_release() {
+    let rf = ReleaseFixture::new("unattended_release");
+    let output = run(rf.new_release().arg("--yes").arg(&rf.rust), true);
+    assert!(stderr(&output).contains("Ready to continue? yes (--yes)"));
+    assert_eq!(
+        rf.git(&["log", "--format=%s", "-2"]),
+        "Update changelog for 1.69\nBump to 0.71.0"
+    );
+}
+
+#[test]
+fn no_terminal_fails() {
+    let rf = ReleaseFixture::new("no_terminal_fails");
+    std::fs::write(rf.cargo.join("src/lib.rs"), "// wip\n").unwrap();
+    let output = run(rf.new_release().arg(&rf.rust), false);
+    assert!(stderr(&output).contains("cannot ask `dirty-tree` without a terminal"));
+}
+
+#[test]
+fn yes_needs_policy_for_dirty_tree() {
+    let rf = ReleaseFixture::new("yes_needs_policy_for_dirty_tree");
+    std::fs::write(rf.cargo.join("src/lib.rs"), "// wip\n").unwrap();
+    let output = run(rf.new_release().arg("--yes").arg(&rf.rust), false);
+    let err = stderr(&output);
+    assert!(err.contains("prompt `dirty-tree` needs a human to decide"));
+    assert!(err.contains("step `check-status` failed"));
+
+    // The policy can accept safety prompts, or decline others.
+    let policy = rf
+        .fixture
+        .prompt_policy("# Unattended release\ndirty-tree = yes\nbump-inspected = no\n");
+    let output = run(
+        rf.new_release()
+            .arg("--no-input")
+            .arg("--prompt-policy")
+            .arg(&policy)
+            .arg(&rf.rust),
+        false,
+    );
+    let err = stderr(&output);
+    assert!(err.contains("Do you want to continue? yes (prompt policy `dirty-tree`)"));
+    assert!(err.contains("Ready to commit? no (prompt policy `bump-inspected`)"));
+    assert!(rf.read("Cargo.toml").contains("\nversion = \"0.71.0\"\n"));
+}
+
+#[test]
+fn browser_batches() {
+    let rf = ReleaseFixture::new("browser_batches");
+    rf.git(&["config", "cargo-new-release.browser-batch", "1"]);
+    // The browser command is split like a shell would.
+    let browser = rf.fixture.path("my browser/open");
+    std::fs::create_dir_all(browser.parent().unwrap()).unwrap();
+    std::fs::copy(rf.fixture.browser_stub(), &browser).unwrap();
+    let policy = rf.fixture.prompt_policy("open-next-batch = no\n");
+    let output = run(
+        rf.new_release()
+            .env(
+                "CARGO_NEW_RELEASE_BROWSER",
+                format!("'{}'", browser.display()),
+            )
+            .arg("--yes")
+            .arg("--prompt-policy")
+            .arg(&policy)
+            .arg("--review-tabs")
+            .arg(&rf.rust),
+        true,
+    );
+    let err = stderr(&output);
+    assert!(
+        err.contains("Opened 1 of 2 URLs, open the next 1? no (prompt policy `open-next-batch`)")
+    );
+    assert!(err.contains("not opened: https://github.com/rust-lang/cargo/pull/104"));
+    assert!(err.contains("not opened: https://github.com/rust-lang/cargo/pull/102"));
+    assert_eq!(
+        rf.fixture.opened_urls(),
+        [
+            "https://github.com/rust-lang/cargo/pull/105",
+            "https://github.com/rust-lang/cargo/pull/103",
+            "https://github.com/tester/cargo/pull/new/version-bump",
+        ]
+    );
+}
+
+#[test]
+fn browser_writes_file() {
+    let rf = ReleaseFixture::new("browser_writes_file");
+    let urls = rf.fixture.root.join("urls.txt");
+    run(
+        rf.new_release()
+            .arg("--yes")
+            .arg("--browser")
+            .arg(format!("file:{}", urls.display()))
+            .arg(&rf.rust),
+        true,
+    );
+    assert!(rf.fixture.opened_urls().is_empty());
+    let urls = std::fs::read_to_string(urls).unwrap();
+    let urls: Vec<_> = urls.lines().collect();
+    assert_eq!(urls.len(), 3);
+    assert!(urls[0].ends_with("/.git/cargo-new-release-review-nightly.html"));
+    assert!(urls[1].ends_with("/.git/cargo-new-release-review-beta.html"));
+    assert_eq!(
+        urls[2],
+        "https://github.com/tester/cargo/pull/new/version-bump"
+    );
+}
+
+#[test]
+fn review_page() {
+    let rf = ReleaseFixture::new("review_page");
+    let mut state = State::default();
+    for pr in 101..=105 {
+        state.issues.insert(pr, None);
+    }
+    state
+        .labels
+        .insert(104, vec!["A-cli".to_string(), "Z-unstable".to_string()]);
+    state.labels.insert(105, vec!["C-bug".to_string()]);
+    let github = GitHubStub::start(state);
+    run(
+        rf.new_release()
+            .arg("--yes")
+            .arg(&rf.rust)
+            .env("GITHUB_API_URL", &github.url)
+            .env("GITHUB_TOKEN", "tester:secret"),
+        true,
+    );
+
+    let md = rf.read(".git/cargo-new-release-review-nightly.md");
+    assert!(md.starts_with("# Cargo 1.70 (nightly) review\n\n2 PRs to review.\n"));
+    assert!(md.contains(
+        "## [#104](https://github.com/rust-lang/cargo/pull/104) Nightly feature\n\
+         \n\
+         by dave · labels: A-cli, Z-unstable\n\
+         \n\
+         > Adds `-Zfoo` <unstable>.\n\
+         \n\
+         Files:\n\
+         - `src/lib.rs`\n\
+         \n\
+         - [ ] Added\n\
+         - [ ] Changed\n\
+         - [ ] Fixed\n\
+         - [x] Nightly only\n"
+    ));
+    let html = rf.read(".git/cargo-new-release-review-nightly.html");
+    assert!(html.contains("<blockquote>Adds `-Zfoo` &lt;unstable&gt;.</blockquote>"));
+    assert!(html.contains("<span>Z-unstable</span>"));
+    assert!(html.contains("<input type=\"checkbox\" name=\"pr-105\" value=\"Nightly only\">"));
+    assert!(html.contains("<input type=\"checkbox\" name=\"pr-105\" value=\"Fixed\" checked>"));
+    // The `C-bug` label wins over the title.
+    let changelog = rf.read(CHANGELOG_PATH);
+    assert!(changelog.contains(
+        "### Fixed\n\
+         \n\
+         - Improve docs \n  [#105](https://github.com/rust-lang/cargo/pull/105)\n\
+         \n\
+         ### Nightly only\n"
+    ));
+    let beta = rf.read(".git/cargo-new-release-review-beta.md");
+    assert!(beta.contains("[#103]"));
+    assert!(beta.contains("[#102]"));
+    assert!(!beta.contains("[#104]"));
+}
+
+#[test]
+fn lint_changelog() {
+    let rf = ReleaseFixture::new("lint_changelog");
+    let output = run(rf.new_release().arg("lint-changelog"), true);
+    assert!(stderr(&output)
+        .contains("src/doc/src/CHANGELOG.md:11: warning: section `Changed` of 1.69 is empty"));
+
+    // Break the changelog upstream, so the release picks it up too.
+    let upstream = rf.fixture.path("cargo-upstream");
+    let changelog = rf
+        .read(CHANGELOG_PATH)
+        .replace("pull/100)", "pull/101)")
+        .replace("(2023-03-09)", "(2023-03-10)");
+    rf.fixture.write(&upstream.join(CHANGELOG_PATH), &changelog);
+    rf.fixture.commit(&upstream, "Break changelog (#106)");
+    rf.git(&["pull", "-q", "upstream", "master"]);
+    let output = run(rf.new_release().arg("lint-changelog"), false);
+    let err = stderr(&output);
+    assert!(err.contains(
+        "src/doc/src/CHANGELOG.md:17: error: 1.68 is dated 2023-03-10, but is scheduled for 2023-03-09"
+    ));
+    assert!(err.contains(
+        "src/doc/src/CHANGELOG.md:23: error: link for #100 points to #101: https://github.com/rust-lang/cargo/pull/101"
+    ));
+    assert!(err.contains("error: src/doc/src/CHANGELOG.md has 2 error(s)"));
+
+    // The release stops before committing a changelog with errors.
+    let policy = rf.fixture.prompt_policy("changelog-lint = no\n");
+    let output = run(
+        rf.new_release()
+            .arg("--yes")
+            .arg("--prompt-policy")
+            .arg(&policy)
+            .arg(&rf.rust),
+        false,
+    );
+    let err = stderr(&output);
+    assert!(err.contains("error: 1.68 is dated 2023-03-10"));
+    assert!(err.contains("Do you want to continue? no (prompt policy `changelog-lint`)"));
+    assert_eq!(rf.git(&["log", "--format=%s", "-1"]), "Bump to 0.71.0");
+}
+
+#[test]
+fn changelog_coverage() {
+    let rf = ReleaseFixture::new("changelog_coverage");
+    let output = run(
+        rf.new_release()
+            .arg("changelog-coverage")
+            .arg(format!("{}..rust-1.69.0", rf.history.start_1_69)),
+        true,
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("3 PRs merged in "));
+    assert!(stdout.contains(
+        "Not in the changelog:\n  \
+         #103 Fix bug B\n    https://github.com/rust-lang/cargo/pull/103\n  \
+         #102 Update changelog\n"
+    ));
+    assert!(!stdout.contains("#101"));
+
+    let output = run(
+        rf.new_release()
+            .arg("changelog-coverage")
+            .arg("rust-1.69.0..master"),
+        false,
+    );
+    assert!(stderr(&output).contains("cannot tell which version `master` is"));
+
+    let output = run(
+        rf.new_release()
+            .args(["changelog-coverage", "--version", "1.68"])
+            .arg(format!("{}..rust-1.69.0", rf.history.start_1_69)),
+        true,
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains(
+        "Documented under another version:\n  \
+         #101 Add feature A\n    in Cargo 1.69 (2023-04-20) (src/doc/src/CHANGELOG.md:9)\n"
+    ));
+    assert!(stdout.contains("In the changelog for 1.68 but not merged in "));
+    assert!(stdout.contains("  #100 (src/doc/src/CHANGELOG.md:23)\n"));
+}
+
+#[test]
+fn bump_kinds() {
+    let rf = ReleaseFixture::new("bump_kinds");
+    let output = run(
+        rf.new_release()
+            .args(["--dry-run", "--bump", "0.71.0-rc.1"])
+            .arg(&rf.rust),
+        true,
+    );
+    assert!(stderr(&output).contains("would run `git commit -a -m 'Bump to 0.71.0-rc.1'`"));
+    let output = run(rf.new_release().args(["--bump", "huge"]), false);
+    assert!(stderr(&output).contains("got `huge`"));
+
+    let upstream = rf.fixture.path("cargo-upstream");
+    rf.fixture
+        .git(&upstream, &["tag", "0.71.0", &rf.history.start_1_69]);
+    let output = run(rf.new_release().arg("--yes").arg(&rf.rust), false);
+    let err = stderr(&output);
+    assert!(err.contains("cannot bump version with `--bump minor`"));
+    assert!(err.contains("0.71.0 has already been released, tag `0.71.0` exists"));
+    assert!(err.contains("step `bump-version` failed"));
+
+    // Stop once the version is bumped.
+    let policy = rf.fixture.root.join("policy");
+    std::fs::write(&policy, "bump-inspected = no\n").unwrap();
+    let output = run(
+        rf.new_release()
+            .args(["--resume", "--bump", "0.72.0-beta.1", "--no-input"])
+            .arg("--prompt-policy")
+            .arg(&policy),
+        false,
+    );
+    assert!(stderr(&output).contains("info: bumping version from 0.70.0 to 0.72.0-beta.1"));
+    assert!(rf
+        .read("Cargo.toml")
+        .contains("\nversion = \"0.72.0-beta.1\"\n"));
+}
+
+#[test]
+fn workspace_members() {
+    let rf = ReleaseFixture::new("workspace_members");
+    let upstream = rf.fixture.path("cargo-upstream");
+    let write = |path: &str, contents: &str| rf.fixture.write(&upstream.join(path), contents);
+    write(
+        "Cargo.toml",
+        "[workspace]\n\
+         members = [\"crates/*\"]\n\
+         \n\
+         [workspace.dependencies]\n\
+         crates-io = { path = \"crates/crates-io\", version = \"0.36.0\" }\n\
+         \n\
+         [package]\n\
+         name = \"cargo\"\n\
+         version = \"0.70.0\"\n\
+         edition = \"2021\"\n",
+    );
+    for (name, version, publish) in [
+        ("crates-io", "0.36.0", ""),
+        ("cargo-util", "0.2.3", ""),
+        ("xtask", "0.1.0", "publish = false\n"),
+    ] {
+        write(
+            &format!("crates/{}/Cargo.toml", name),
+            &format!(
+                "[package]\nname = \"{}\"\nversion = \"{}\"\n{}",
+                name, version, publish
+            ),
+        );
+        write(&format!("crates/{}/src/lib.rs", name), "");
+    }
+    write(
+        "crates/cargo-util/Cargo.toml",
+        "[package]\n\
+         name = \"cargo-util\"\n\
+         version = \"0.2.3\"\n\
+         \n\
+         [dev-dependencies]\n\
+         crates-io.workspace = true\n",
+    );
+    write(
+        "Cargo.lock",
+        "version = 3\n\
+         \n\
+         [[package]]\n\
+         name = \"cargo\"\n\
+         version = \"0.70.0\"\n\
+         \n\
+         [[package]]\n\
+         name = \"cargo-util\"\n\
+         version = \"0.2.3\"\n\
+         dependencies = [\n \"crates-io\",\n]\n\
+         \n\
+         [[package]]\n\
+         name = \"crates-io\"\n\
+         version = \"0.36.0\"\n\
+         \n\
+         [[package]]\n\
+         name = \"xtask\"\n\
+         version = \"0.1.0\"\n",
+    );
+    rf.fixture.commit(&upstream, "Add workspace members");
+    rf.fixture.git(&upstream, &["tag", "0.69.0"]);
+    write("crates/crates-io/src/lib.rs", "pub fn publish() {}\n");
+    write("crates/xtask/src/lib.rs", "pub fn main() {}\n");
+    rf.fixture.commit(&upstream, "Change crates-io and xtask");
+    rf.git(&["pull", "-q", "--tags", "upstream", "master"]);
+
+    let output = run(rf.new_release().arg("--dry-run").arg(&rf.rust), true);
+    let err = stderr(&output);
+    assert!(err.contains(
+        "Workspace members changed since 0.69.0 without a new version:\n  \
+         crates-io 0.36.0 -> 0.36.1 (crates/crates-io)\n\
+         dry-run: would write Cargo.toml:"
+    ));
+    assert!(err.contains("dry-run: would write crates/crates-io/Cargo.toml:"));
+    assert!(!err.contains("cargo-util 0.2.3"));
+    assert!(!err.contains("xtask 0.1.0"));
+
+    // Members can get a bigger bump than a patch.
+    let output = run(
+        rf.new_release()
+            .args(["--dry-run", "--bump-member", "crates-io=minor"])
+            .arg(&rf.rust),
+        true,
+    );
+    assert!(stderr(&output).contains("  crates-io 0.36.0 -> 0.37.0 (crates/crates-io)\n"));
+    assert!(String::from_utf8_lossy(&output.stdout)
+        .contains("+crates-io = { path = \"crates/crates-io\", version = \"0.37.0\" }"));
+    let output = run(
+        rf.new_release()
+            .args(["--dry-run", "--bump-member", "crates_io=minor"])
+            .arg(&rf.rust),
+        false,
+    );
+    assert!(stderr(&output).contains("--bump-member `crates_io` is not a workspace member"));
+
+    // Accept the member bumps, then stop once the versions are bumped.
+    let policy = rf.fixture.prompt_policy("bump-inspected = no\n");
+    run(
+        rf.new_release()
+            .arg("--yes")
+            .arg("--prompt-policy")
+            .arg(&policy)
+            .arg(&rf.rust),
+        false,
+    );
+    let root = rf.read("Cargo.toml");
+    assert!(root.contains("crates-io = { path = \"crates/crates-io\", version = \"0.36.1\" }\n"));
+    assert!(root.contains("\nversion = \"0.71.0\"\n"));
+    assert!(rf
+        .read("crates/crates-io/Cargo.toml")
+        .contains("\nversion = \"0.36.1\"\n"));
+    let lock = rf.read("Cargo.lock");
+    assert!(lock.contains("name = \"cargo\"\nversion = \"0.71.0\"\n"));
+    assert!(lock.contains("name = \"crates-io\"\nversion = \"0.36.1\"\n"));
+    assert!(lock.contains("name = \"cargo-util\"\nversion = \"0.2.3\"\n"));
+    assert!(rf
+        .read("crates/cargo-util/Cargo.toml")
+        .contains("\nversion = \"0.2.3\"\n"));
+    assert!(rf
+        .read("crates/xtask/Cargo.toml")
+        .contains("\nversion = \"0.1.0\"\n"));
+
+    run(rf.new_release().args(["--resume", "--yes"]), true);
+    assert_eq!(
+        rf.git(&["show", "--format=%s", "--name-only", "HEAD~1"]),
+        "Bump to 0.71.0\n\nCargo.lock\nCargo.toml\ncrates/crates-io/Cargo.toml"
+    );
+}
+
+#[test]
+fn lockfile_drift() {
+    let rf = ReleaseFixture::new("lockfile_drift");
+    let upstream = rf.fixture.path("cargo-upstream");
+    rf.fixture.write(
+        &upstream.join("Cargo.lock"),
+        "version = 3\n\
+         \n\
+         [[package]]\n\
+         name = \"cargo\"\n\
+         version = \"0.69.0\"\n\
+         dependencies = [\n \"semver\",\n]\n",
+    );
+    rf.fixture.commit(&upstream, "Add a stale lockfile");
+    rf.git(&["pull", "-q", "upstream", "master"]);
+
+    let output = run(rf.new_release().arg("--dry-run").arg(&rf.rust), false);
+    assert!(stderr(&output).contains(
+        "Cargo.lock does not match the workspace before the version bump:\n  \
+         `cargo` is locked at 0.69.0, but Cargo.toml has 0.70.0\n  \
+         `cargo` is locked with a dependency on `semver`, which Cargo.toml does not have\n\
+         update it on master before releasing"
+    ));
+}
+
+#[test]
+fn version_gates() {
+    let rf = ReleaseFixture::new("version_gates");
+    let output = run(rf.new_release().arg("--dry-run").arg(&rf.rust), true);
+    assert!(stderr(&output).contains("No version-dependent code found.\n"));
+
+    let upstream = rf.fixture.path("cargo-upstream");
+    rf.fixture.write(
+        &upstream.join("tests/testsuite/split.rs"),
+        "#[cargo_test(>=1.65, reason = \"needs split-debuginfo\")]\n\
+         fn split() {}\n\
+         \n\
+         #[cargo_test(nightly, reason = \"-Zfoo is unstable\")]\n\
+         fn foo() {}\n",
+    );
+    rf.fixture.commit(&upstream, "Add a test");
+    rf.git(&["pull", "-q", "upstream", "master"]);
+    let output = run(rf.new_release().arg("--dry-run").arg(&rf.rust), true);
+    assert!(stderr(&output).contains(
+        "Check whether these can be updated now that Rust 1.68 is stable:\n  \
+         [ ] tests/testsuite/split.rs:1: requires Rust 1.65, which is stable\n\
+         Check whether these nightly-only tests and checks cover anything stabilized in 1.68:\n  \
+         [ ] tests/testsuite/split.rs:4: test only runs on nightly: -Zfoo is unstable\n\
+         Check for any other rustc probing (usually target_info.rs) that can be updated.\n"
+    ));
+}
+
+#[test]
+fn schedule() {
+    let rf = ReleaseFixture::new("schedule");
+    let output = run(rf.new_release().args(["schedule", "2023-05-01"]), true);
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "On 2023-05-01:\n  \
+           stable   1.69 (released 2023-04-20)\n  \
+           beta     1.70 (due 2023-06-01)\n  \
+           nightly  1.71 (due 2023-07-13)\n\
+         1.71 branches to beta in 31 day(s), on 2023-06-01\n"
+    );
+
+    let exceptions = rf.fixture.path("exceptions");
+    std::fs::write(&exceptions, "1.70 = 2023-06-08\n").unwrap();
+    rf.git(&[
+        "config",
+        "cargo-new-release.schedule-exceptions",
+        exceptions.to_str().unwrap(),
+    ]);
+    let output = run(rf.new_release().args(["schedule", "1.70"]), true);
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "1.70 is nightly from 2023-03-09\n\
+         1.70 is beta from 2023-04-20\n\
+         1.70 is released on 2023-06-08\n"
+    );
+
+    let output = run(
+        rf.new_release()
+            .args(["schedule", "--schedule-exceptions", "missing", "1.70"]),
+        false,
+    );
+    assert!(stderr(&output).contains("failed to read schedule exceptions missing"));
+    let output = run(rf.new_release().args(["schedule", "1.99999999999"]), false);
+    assert!(stderr(&output).contains("error: 1.99999999997 is too far in the future to schedule"));
+    let output = run(rf.new_release().args(["schedule", "June"]), false);
+    assert!(stderr(&output).contains("expected a date like 2023-06-01 or a version like 1.70"));
+}
+
+#[test]
+fn verbose() {
+    let rf = ReleaseFixture::new("verbose");
+    let coverage = format!("{}..rust-1.69.0", rf.history.start_1_69);
+    for args in [
+        vec!["schedule", "-v", "2023-05-01"],
+        vec!["-v", "lint-changelog"],
+        vec!["changelog-coverage", "--verbose", &coverage],
+    ] {
+        let output = run(rf.new_release().args(&args), true);
+        let err = stderr(&output);
+        assert!(err.contains("running `git "), "{:?}", args);
+        assert!(err.contains("    finished in "), "{:?}", args);
+        assert!(!err.contains("    stdout:"), "{:?}", args);
+    }
+
+    // `-vv` also logs what commands print.
+    let output = run(rf.new_release().args(["lint-changelog", "-vv"]), true);
+    let err = stderr(&output);
+    assert!(err.contains("running `git rev-parse --show-toplevel` in "));
+    assert!(err.contains(&format!("    stdout:\n        {}\n", rf.cargo.display())));
+
+    let output = run(
+        rf.new_release().args(["schedule", "-x", "2023-05-01"]),
+        false,
+    );
+    assert!(stderr(&output).contains("unknown option `-x`"));
+}
+
+#[test]
+fn rust_versions() {
+    let rf = ReleaseFixture::new("rust_versions");
+    // 1.69 was never released, so stable is still on it.
+    let rust_upstream = rf.fixture.path("rust-upstream");
+    rf.fixture
+        .git(&rust_upstream, &["branch", "-f", "stable", "beta"]);
+
+    let output = run(rf.new_release().arg("--yes").arg(&rf.rust), false);
+    assert!(stderr(&output).contains(
+        "rust-lang/rust has 1.69.0 on beta and 1.69.0 on stable, \
+         but cargo 0.71.0 expects 1.69.0 on beta and 1.68.0 on stable\n\
+         Check whether a Rust release was skipped or delayed before continuing."
+    ));
+    assert_eq!(rf.git(&["log", "--format=%s", "-1"]), "Bump to 0.71.0");
+    // The changelog is left alone.
+    assert_eq!(rf.git(&["status", "--porcelain"]), "");
+}
+
+#[test]
+fn patch_release() {
+    let rf = ReleaseFixture::new("patch_release");
+    let changelog = rf.read(CHANGELOG_PATH);
+    let output = run(
+        rf.new_release()
+            .args(["--yes", "--bump", "patch"])
+            .arg(&rf.rust),
+        true,
+    );
+    let stderr = stderr(&output);
+    assert!(stderr.contains("info: creating version-bump branch from upstream/rust-1.69.0"));
+    assert!(stderr.contains("info: 0.70.1 is not a new minor version, skipping the changelog"));
+    assert!(stderr.contains("title:\nBump to 0.70.1\n"));
+    // The bump is on the 1.69 release branch, not master.
+    assert_eq!(
+        rf.git(&["log", "--format=%s", "-2"]),
+        "Bump to 0.70.1\nFix bug B (#103)"
+    );
+    assert!(rf.read("Cargo.toml").contains("\nversion = \"0.70.1\"\n"));
+    assert_eq!(rf.read(CHANGELOG_PATH), changelog);
+
+    // `--base` picks the branch instead.
+    let output = run(
+        rf.new_release()
+            .args(["--dry-run", "--bump", "patch", "--base", "upstream/master"])
+            .arg(&rf.rust),
+        true,
+    );
+    assert!(testsupport::stderr(&output)
+        .contains("dry-run: would run `git checkout -B version-bump upstream/master`"));
+}
+
+#[test]
+fn rust_remote() {
+    let rf = ReleaseFixture::new("rust_remote");
+    rf.fixture
+        .git(&rf.rust, &["remote", "rename", "upstream", "rust-lang"]);
+    // The CLI backend, for the error from the failing command.
+    let output = run(
+        rf.new_release()
+            .env("CARGO_NEW_RELEASE_GIT", "cli")
+            .arg("--yes")
+            .arg(&rf.rust),
+        false,
+    );
+    let err = stderr(&output);
+    assert!(err.contains(
+        "caused by: failed to read the Rust version at upstream/stable\n\
+         caused by: failed to run `git cat-file blob upstream/stable:src/version`: exit status"
+    ));
+    assert!(err.contains("caused by: fatal: "));
+
+    rf.git(&["config", "cargo-new-release.rust-remote", "rust-lang"]);
+    run(rf.new_release().args(["--yes", "--resume"]), true);
+    assert_eq!(
+        rf.git(&["log", "--format=%s", "-2"]),
+        "Update changelog for 1.69\nBump to 0.71.0"
+    );
+}
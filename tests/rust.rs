@@ -0,0 +1,56 @@
+//! Tests for reading the rust repo.
+
+#![cfg(unix)]
+
+mod testsupport;
+
+use cargo_new_release::git;
+use cargo_new_release::rust::{self, SubmoduleError};
+use semver::Version;
+use testsupport::ReleaseFixture;
+
+#[test]
+fn cargo_commit_at() {
+    let rf = ReleaseFixture::new("rust_cargo_commit_at");
+    let repo = git::open(&rf.fixture.path("rust-upstream")).unwrap();
+
+    assert_eq!(
+        rust::cargo_commit_at(&*repo, "beta").unwrap(),
+        rf.history.beta
+    );
+    assert_eq!(
+        rust::cargo_commit_at(&*repo, "master").unwrap(),
+        rf.history.master
+    );
+    assert_eq!(
+        rust::version_at(&*repo, "beta").unwrap(),
+        Version::new(1, 69, 0)
+    );
+    assert_eq!(
+        rust::version_at(&*repo, "stable").unwrap(),
+        Version::new(1, 68, 2)
+    );
+
+    let error = rust::cargo_commit_at(&*repo, "stable").unwrap_err();
+    assert_eq!(
+        error.downcast_ref::<SubmoduleError>(),
+        Some(&SubmoduleError::Missing {
+            rev: "stable".to_string(),
+            path: "src/tools/cargo".to_string(),
+        })
+    );
+    assert_eq!(
+        error.to_string(),
+        "`src/tools/cargo` does not exist at stable"
+    );
+
+    let error = rust::submodule_commit(&*repo, "master", "src").unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "`src` at master is a tree, not a submodule"
+    );
+    assert!(matches!(
+        error.downcast_ref::<SubmoduleError>(),
+        Some(SubmoduleError::NotGitlink { kind, .. }) if kind == "tree"
+    ));
+}
@@ -0,0 +1,248 @@
+//! A minimal stand-in for the GitHub REST API.
+//!
+//! Only the issue and milestone endpoints used by `milestone` are
+//! supported. Each connection serves a single request.
+
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+#[derive(Clone, Debug)]
+pub struct Milestone {
+    pub number: u64,
+    pub title: String,
+}
+
+#[derive(Default)]
+pub struct State {
+    /// Known issues and PRs, mapped to their milestone number.
+    pub issues: HashMap<u32, Option<u64>>,
+    pub milestones: Vec<Milestone>,
+    /// Labels on issues and PRs.
+    pub labels: HashMap<u32, Vec<String>>,
+    /// Every request received, as `METHOD path`.
+    pub requests: Vec<String>,
+}
+
+impl State {
+    /// Adds a milestone, returning its number.
+    pub fn add_milestone(&mut self, title: &str) -> u64 {
+        let number = self.milestones.len() as u64 + 1;
+        self.milestones.push(Milestone {
+            number,
+            title: title.to_string(),
+        });
+        number
+    }
+
+    /// Returns the title of the milestone set on the given issue.
+    pub fn milestone_of(&self, issue: u32) -> Option<String> {
+        let number = self.issues[&issue]?;
+        self.milestones
+            .iter()
+            .find(|m| m.number == number)
+            .map(|m| m.title.clone())
+    }
+
+    fn milestone_json(&self, number: u64) -> Value {
+        let m = self.milestones.iter().find(|m| m.number == number).unwrap();
+        json!({"number": m.number, "title": m.title})
+    }
+}
+
+pub struct GitHubStub {
+    /// The base URL, for `GITHUB_API_URL`.
+    pub url: String,
+    pub state: Arc<Mutex<State>>,
+}
+
+impl GitHubStub {
+    /// Starts the server on a random local port.
+    pub fn start(state: State) -> GitHubStub {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let state = Arc::new(Mutex::new(state));
+        let server_state = Arc::clone(&state);
+        let base_url = url.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let stream = match stream {
+                    Ok(stream) => stream,
+                    Err(_) => break,
+                };
+                handle(stream, &server_state, &base_url);
+            }
+        });
+        GitHubStub { url, state }
+    }
+}
+
+struct Request {
+    method: String,
+    path: String,
+    query: HashMap<String, String>,
+    authorized: bool,
+    body: Value,
+}
+
+fn read_request(stream: &TcpStream) -> Request {
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    reader.read_line(&mut line).unwrap();
+    let mut parts = line.split_whitespace();
+    let method = parts.next().unwrap().to_string();
+    let target = parts.next().unwrap().to_string();
+    let mut content_length = 0;
+    let mut authorized = false;
+    loop {
+        let mut header = String::new();
+        reader.read_line(&mut header).unwrap();
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        let (name, value) = header.split_once(':').unwrap();
+        match name.to_ascii_lowercase().as_str() {
+            "content-length" => content_length = value.trim().parse().unwrap(),
+            "authorization" => authorized = value.trim().starts_with("Basic "),
+            _ => {}
+        }
+    }
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).unwrap();
+    let (path, query) = match target.split_once('?') {
+        Some((path, query)) => (path.to_string(), query),
+        None => (target.clone(), ""),
+    };
+    let query = query
+        .split('&')
+        .filter_map(|kv| kv.split_once('='))
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+    Request {
+        method,
+        path,
+        query,
+        authorized,
+        body: serde_json::from_slice(&body).unwrap_or(Value::Null),
+    }
+}
+
+fn handle(mut stream: TcpStream, state: &Mutex<State>, base_url: &str) {
+    let request = read_request(&stream);
+    let mut state = state.lock().unwrap();
+    state
+        .requests
+        .push(format!("{} {}", request.method, request.path));
+    let (status, headers, body) = if request.authorized {
+        respond(&mut state, &request, base_url)
+    } else {
+        (
+            401,
+            Vec::new(),
+            json!({"message": "Requires authentication"}),
+        )
+    };
+    let body = body.to_string();
+    let mut response = format!(
+        "HTTP/1.1 {} Stub\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n",
+        status,
+        body.len()
+    );
+    for (name, value) in headers {
+        response.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    response.push_str("\r\n");
+    response.push_str(&body);
+    stream.write_all(response.as_bytes()).unwrap();
+}
+
+fn respond(
+    state: &mut State,
+    request: &Request,
+    base_url: &str,
+) -> (u16, Vec<(String, String)>, Value) {
+    let not_found = (404, Vec::new(), json!({"message": "Not Found"}));
+    let segments: Vec<_> = request.path.trim_start_matches('/').split('/').collect();
+    let (owner, repo, rest) = match &segments[..] {
+        ["repos", owner, repo, rest @ ..] => (owner, repo, rest),
+        _ => return not_found,
+    };
+    match (request.method.as_str(), rest) {
+        (method @ ("GET" | "PATCH"), ["issues", number]) => {
+            let number: u32 = number.parse().unwrap();
+            if !state.issues.contains_key(&number) {
+                return not_found;
+            }
+            if method == "PATCH" {
+                let milestone = request.body["milestone"].as_u64();
+                state.issues.insert(number, milestone);
+            }
+            let milestone = match state.issues[&number] {
+                Some(m) => state.milestone_json(m),
+                None => Value::Null,
+            };
+            (
+                200,
+                Vec::new(),
+                json!({
+                    "number": number,
+                    "milestone": milestone,
+                    "labels": state
+                        .labels
+                        .get(&number)
+                        .into_iter()
+                        .flatten()
+                        .map(|name| json!({"name": name}))
+                        .collect::<Vec<_>>(),
+                }),
+            )
+        }
+        ("POST", ["milestones"]) => {
+            let title = request.body["title"].as_str().unwrap();
+            if state.milestones.iter().any(|m| m.title == title) {
+                return (
+                    422,
+                    Vec::new(),
+                    json!({"message": "Validation Failed", "errors": [{"code": "already_exists"}]}),
+                );
+            }
+            let number = state.add_milestone(title);
+            (201, Vec::new(), state.milestone_json(number))
+        }
+        ("GET", ["milestones"]) => {
+            let per_page: usize = request
+                .query
+                .get("per_page")
+                .map_or(30, |n| n.parse().unwrap());
+            let page: usize = request.query.get("page").map_or(1, |n| n.parse().unwrap());
+            let start = (page - 1) * per_page;
+            let items: Vec<_> = state
+                .milestones
+                .iter()
+                .skip(start)
+                .take(per_page)
+                .map(|m| state.milestone_json(m.number))
+                .collect();
+            let mut headers = Vec::new();
+            if start + per_page < state.milestones.len() {
+                headers.push((
+                    "Link".to_string(),
+                    format!(
+                        "<{}/repos/{}/{}/milestones?state=all&per_page={}&page={}>; rel=\"next\"",
+                        base_url,
+                        owner,
+                        repo,
+                        per_page,
+                        page + 1
+                    ),
+                ));
+            }
+            (200, headers, Value::Array(items))
+        }
+        _ => not_found,
+    }
+}
@@ -0,0 +1,411 @@
+//! Helpers for building throwaway git repositories that look enough like
+//! rust-lang/cargo and rust-lang/rust to drive the release tools.
+
+#![allow(dead_code)]
+
+pub mod github;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+/// A set of repositories in a temporary directory.
+pub struct Fixture {
+    pub root: PathBuf,
+}
+
+impl Fixture {
+    /// Creates an empty fixture directory, removing any previous one with
+    /// the same name.
+    pub fn new(name: &str) -> Fixture {
+        let root = Path::new(env!("CARGO_TARGET_TMPDIR"))
+            .join("fixtures")
+            .join(name);
+        if root.exists() {
+            fs::remove_dir_all(&root).unwrap();
+        }
+        fs::create_dir_all(root.join("home")).unwrap();
+        Fixture { root }
+    }
+
+    pub fn path(&self, path: &str) -> PathBuf {
+        self.root.join(path)
+    }
+
+    /// Returns a `git` command isolated from the user's configuration.
+    pub fn git_cmd(&self, dir: &Path) -> Command {
+        let mut cmd = Command::new("git");
+        cmd.current_dir(dir);
+        self.isolate(&mut cmd);
+        cmd
+    }
+
+    /// Runs `git` in the given directory, panicking on failure.
+    pub fn git(&self, dir: &Path, args: &[&str]) -> String {
+        let output = self.git_cmd(dir).args(args).output().unwrap();
+        if !output.status.success() {
+            panic!(
+                "git {:?} failed in {}:\n{}",
+                args,
+                dir.display(),
+                String::from_utf8_lossy(&output.stderr)
+            );
+        }
+        String::from_utf8(output.stdout).unwrap().trim().to_string()
+    }
+
+    /// Sets up the environment so that commands do not depend on the user's
+    /// configuration or environment.
+    pub fn isolate(&self, cmd: &mut Command) {
+        cmd.env("HOME", self.path("home"))
+            .env("XDG_CONFIG_HOME", self.path("home"))
+            .env("GIT_CONFIG_NOSYSTEM", "1")
+            .env("GIT_AUTHOR_NAME", "Test Author")
+            .env("GIT_AUTHOR_EMAIL", "author@example.com")
+            .env("GIT_COMMITTER_NAME", "Test Committer")
+            .env("GIT_COMMITTER_EMAIL", "committer@example.com")
+            .env_remove("CARGO_NEW_RELEASE_BROWSER")
+            .env_remove("CARGO_NEW_RELEASE_GIT")
+            .env_remove("CARGO_NEW_RELEASE_VERBOSE")
+            .env_remove("BROWSER")
+            .env_remove("GITHUB_API_URL")
+            .env_remove("GITHUB_TOKEN");
+    }
+
+    /// Writes a file, creating parent directories as needed.
+    pub fn write(&self, path: &Path, contents: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    /// Commits everything in the repo, returning the new commit hash.
+    pub fn commit(&self, dir: &Path, message: &str) -> String {
+        self.git(dir, &["add", "-A"]);
+        self.git(dir, &["commit", "-q", "--allow-empty", "-m", message]);
+        self.git(dir, &["rev-parse", "HEAD"])
+    }
+
+    /// Writes a prompt policy file, for `--prompt-policy`.
+    pub fn prompt_policy(&self, contents: &str) -> PathBuf {
+        let path = self.path("prompt-policy");
+        self.write(&path, contents);
+        path
+    }
+
+    /// Creates a browser stub that records the URLs it is asked to open.
+    ///
+    /// Returns the path of the stub, for `CARGO_NEW_RELEASE_BROWSER`.
+    pub fn browser_stub(&self) -> PathBuf {
+        use std::os::unix::fs::PermissionsExt;
+        let path = self.path("browser");
+        let log = self.path("browser.log");
+        self.write(
+            &path,
+            &format!("#!/bin/sh\nprintf '%s\\n' \"$@\" >> '{}'\n", log.display()),
+        );
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+        path
+    }
+
+    /// Returns the URLs passed to the browser stub.
+    pub fn opened_urls(&self) -> Vec<String> {
+        fs::read_to_string(self.path("browser.log"))
+            .unwrap_or_default()
+            .lines()
+            .map(String::from)
+            .collect()
+    }
+}
+
+/// Commits made in the fixture's upstream cargo repo.
+pub struct CargoHistory {
+    /// The commit the 1.69 changelog section starts from.
+    pub start_1_69: String,
+    /// The commit `rust-1.69.0` and rust's beta branch point to.
+    pub beta: String,
+    /// The tip of master.
+    pub master: String,
+    /// Every commit on master, oldest first.
+    pub all: Vec<String>,
+}
+
+/// A cargo checkout and a rust checkout with their upstreams.
+///
+/// The cargo repo is at version 0.70.0 (nightly 1.69). The 1.69 beta branch
+/// contains PRs #101 (already in the changelog), #102, and #103. Master
+/// additionally has #104 and #105.
+pub struct ReleaseFixture {
+    pub fixture: Fixture,
+    pub history: CargoHistory,
+    /// The cargo checkout the release is run from.
+    pub cargo: PathBuf,
+    /// The rust checkout.
+    pub rust: PathBuf,
+    /// The bare repo standing in for the user's fork.
+    pub origin: PathBuf,
+}
+
+pub const CHANGELOG_PATH: &str = "src/doc/src/CHANGELOG.md";
+
+impl ReleaseFixture {
+    pub fn new(name: &str) -> ReleaseFixture {
+        let fixture = Fixture::new(name);
+        let history = cargo_upstream(&fixture);
+        rust_upstream(&fixture, &history);
+
+        let cargo = fixture.path("cargo");
+        let upstream = fixture.path("cargo-upstream");
+        fixture.git(
+            &fixture.root,
+            &[
+                "clone",
+                "-q",
+                "-o",
+                "upstream",
+                upstream.to_str().unwrap(),
+                "cargo",
+            ],
+        );
+        let origin = fixture.path("origin.git");
+        fixture.git(
+            &fixture.root,
+            &["init", "-q", "--bare", origin.to_str().unwrap()],
+        );
+        // The tool checks the remote URLs, so use GitHub-looking URLs that
+        // are redirected to the local repos.
+        for (url, path, key) in [
+            (
+                "https://github.com/rust-lang/cargo.git",
+                &upstream,
+                "insteadOf",
+            ),
+            (
+                "https://github.com/tester/cargo.git",
+                &origin,
+                "pushInsteadOf",
+            ),
+        ] {
+            fixture.git(
+                &cargo,
+                &["config", &format!("url.{}.{}", path.display(), key), url],
+            );
+        }
+        fixture.git(
+            &cargo,
+            &[
+                "config",
+                "remote.upstream.url",
+                "https://github.com/rust-lang/cargo.git",
+            ],
+        );
+        fixture.git(
+            &cargo,
+            &[
+                "remote",
+                "add",
+                "origin",
+                "https://github.com/tester/cargo.git",
+            ],
+        );
+
+        let rust_upstream = fixture.path("rust-upstream");
+        fixture.git(
+            &fixture.root,
+            &[
+                "clone",
+                "-q",
+                "-o",
+                "upstream",
+                rust_upstream.to_str().unwrap(),
+                "rust",
+            ],
+        );
+        let rust = fixture.path("rust");
+        ReleaseFixture {
+            fixture,
+            history,
+            cargo,
+            rust,
+            origin,
+        }
+    }
+
+    /// Checks out `src/tools/cargo` in the rust repo.
+    pub fn checkout_rust_cargo(&self) {
+        let upstream = self.fixture.path("cargo-upstream");
+        self.fixture.git(
+            &self.rust,
+            &["clone", "-q", upstream.to_str().unwrap(), "src/tools/cargo"],
+        );
+    }
+
+    /// Returns a command to run `milestone` on the rust checkout.
+    pub fn milestone(&self, github: &github::GitHubStub) -> Command {
+        let mut cmd = Command::new(env!("CARGO_BIN_EXE_milestone"));
+        cmd.arg(&self.rust);
+        self.fixture.isolate(&mut cmd);
+        cmd.env("GITHUB_API_URL", &github.url)
+            .env("GITHUB_TOKEN", "tester:secret");
+        cmd
+    }
+
+    /// Returns a command to run `cargo-new-release` in the cargo checkout.
+    pub fn new_release(&self) -> Command {
+        let mut cmd = Command::new(env!("CARGO_BIN_EXE_cargo-new-release"));
+        cmd.current_dir(&self.cargo);
+        self.fixture.isolate(&mut cmd);
+        cmd.env("CARGO_NEW_RELEASE_BROWSER", self.fixture.browser_stub());
+        cmd
+    }
+
+    pub fn git(&self, args: &[&str]) -> String {
+        self.fixture.git(&self.cargo, args)
+    }
+
+    pub fn read(&self, path: &str) -> String {
+        fs::read_to_string(self.cargo.join(path)).unwrap()
+    }
+}
+
+/// Runs a command, returning its output and panicking if its success does
+/// not match `success`.
+pub fn run(cmd: &mut Command, success: bool) -> Output {
+    let output = cmd.output().unwrap();
+    if output.status.success() != success {
+        panic!(
+            "expected success={} for {:?}, got {}\nstdout:\n{}\nstderr:\n{}",
+            success,
+            cmd,
+            output.status,
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+    output
+}
+
+pub fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+fn short(hash: &str) -> &str {
+    &hash[..8]
+}
+
+/// Creates the upstream cargo repo.
+fn cargo_upstream(f: &Fixture) -> CargoHistory {
+    let dir = f.path("cargo-upstream");
+    f.git(
+        &f.root,
+        &["init", "-q", "-b", "master", dir.to_str().unwrap()],
+    );
+    f.write(
+        &dir.join("Cargo.toml"),
+        "[package]\nname = \"cargo\"\nversion = \"0.70.0\"\nedition = \"2021\"\n",
+    );
+    f.write(&dir.join("src/lib.rs"), "");
+    let start_1_69 = f.commit(&dir, "Initial commit");
+
+    let pr_101 = f.commit(
+        &dir,
+        "Auto merge of #101 - alice:feature-a, r=bob\n\nAdd feature A",
+    );
+    f.write(
+        &dir.join(CHANGELOG_PATH),
+        &format!(
+            "# Changelog\n\
+             \n\
+             ## Cargo 1.69 (2023-04-20)\n\
+             [{s}...HEAD](https://github.com/rust-lang/cargo/compare/{s}...HEAD)\n\
+             \n\
+             ### Added\n\
+             \n\
+             - Added feature A.\n  \
+               [#101](https://github.com/rust-lang/cargo/pull/101)\n\
+             \n\
+             ### Changed\n\
+             \n\
+             ### Fixed\n\
+             \n\
+             ### Nightly only\n\
+             \n\
+             ## Cargo 1.68 (2023-03-09)\n\
+             [0123abcd...{s}](https://github.com/rust-lang/cargo/compare/0123abcd...{s})\n\
+             \n\
+             ### Added\n\
+             \n\
+             - Something old.\n  \
+               [#100](https://github.com/rust-lang/cargo/pull/100)\n",
+            s = short(&start_1_69)
+        ),
+    );
+    let pr_102 = f.commit(
+        &dir,
+        "Merge pull request #102 from carol/changelog\n\nUpdate changelog",
+    );
+    let beta = f.commit(&dir, "Fix bug B (#103)");
+    f.git(&dir, &["branch", "rust-1.69.0"]);
+    f.write(&dir.join("src/lib.rs"), "pub fn foo() {}\n");
+    let pr_104 = f.commit(
+        &dir,
+        "Auto merge of #104 - dave:nightly, r=bob\n\nNightly feature\n\nAdds `-Zfoo` <unstable>.",
+    );
+    let master = f.commit(&dir, "Improve docs (#105)");
+    CargoHistory {
+        all: vec![
+            start_1_69.clone(),
+            pr_101,
+            pr_102,
+            beta.clone(),
+            pr_104,
+            master.clone(),
+        ],
+        start_1_69,
+        beta,
+        master,
+    }
+}
+
+/// Creates the upstream rust repo.
+///
+/// Each rust commit updates `src/tools/cargo` by one cargo commit. The
+/// first points at the initial cargo commit, and `beta` is at 1.69.0.
+/// `stable` is an earlier commit at 1.68.2 without the submodule.
+fn rust_upstream(f: &Fixture, history: &CargoHistory) {
+    let dir = f.path("rust-upstream");
+    f.git(
+        &f.root,
+        &["init", "-q", "-b", "master", dir.to_str().unwrap()],
+    );
+    let set_cargo = |hash: &str, version: &str| {
+        f.write(&dir.join("src/version"), &format!("{}\n", version));
+        f.git(&dir, &["add", "src/version"]);
+        f.git(
+            &dir,
+            &[
+                "update-index",
+                "--add",
+                "--cacheinfo",
+                &format!("160000,{},src/tools/cargo", hash),
+            ],
+        );
+    };
+    // `Fixture::commit` would stage the removal of the submodule, since it
+    // isn't checked out.
+    let commit = |message: &str| f.git(&dir, &["commit", "-q", "-m", message]);
+    f.write(&dir.join("src/version"), "1.68.2\n");
+    f.git(&dir, &["add", "src/version"]);
+    commit("Bump to 1.68.2");
+    f.git(&dir, &["branch", "stable"]);
+    for (i, hash) in history.all.iter().enumerate() {
+        let version = if i <= 3 { "1.69.0" } else { "1.70.0" };
+        set_cargo(hash, version);
+        commit(&format!(
+            "Auto merge of #{} - x:y, r=z\n\nUpdate cargo",
+            5000 + i
+        ));
+        if *hash == history.beta {
+            f.git(&dir, &["branch", "beta"]);
+        }
+    }
+}
@@ -0,0 +1,56 @@
+//! Tests for finding code that depends on the Rust version.
+
+use cargo_new_release::version_gates;
+
+#[test]
+fn find() {
+    let contents = r#"use cargo_test_support::prelude::*;
+
+#[cargo_test(nightly, reason = "-Zfoo is unstable")]
+fn foo() {}
+
+#[cargo_test(>=1.68, reason = "needs --print=split-debuginfo")]
+fn bar() {}
+
+#[cargo_test(>=1.69, reason = "needs a newer rustc")]
+fn baz() {}
+
+fn probe(config: &Config, rustc_version: &semver::Version) -> bool {
+    // Remove this once 1.67 is the minimum supported version.
+    if *rustc_version >= semver::Version::new(1, 66, 0) {
+        return config.nightly_features_allowed;
+    }
+    // 1.70 is stable in June.
+    *rustc_version < semver::Version::new(1, 70, 0)
+}
+"#;
+    let gates: Vec<_> = version_gates::find("tests/testsuite/foo.rs", contents, 68)
+        .iter()
+        .map(|gate| gate.to_string())
+        .collect();
+    assert_eq!(
+        gates,
+        [
+            "tests/testsuite/foo.rs:3: test only runs on nightly: -Zfoo is unstable",
+            "tests/testsuite/foo.rs:6: requires Rust 1.68, which is stable",
+            "tests/testsuite/foo.rs:13: mentions Rust 1.67, which is stable",
+            "tests/testsuite/foo.rs:14: requires Rust 1.66, which is stable",
+            "tests/testsuite/foo.rs:15: checks `nightly_features_allowed`",
+        ]
+    );
+}
+
+#[test]
+fn find_ignores_requirements() {
+    let contents = r#"Package::new("foo", "1.0.0").dep("bar", ">=1.5").publish();
+let req = VersionReq::parse(">=1.5").unwrap();
+assert!(req.matches(&Version::new(1, 6, 0)));
+p.cargo("add foo@>=1.2").run();
+if rustc.version >= Version::new(1, 60, 0) {}
+"#;
+    let gates: Vec<_> = version_gates::find("src/foo.rs", contents, 68)
+        .iter()
+        .map(|gate| gate.to_string())
+        .collect();
+    assert_eq!(gates, ["src/foo.rs:5: requires Rust 1.60, which is stable"]);
+}
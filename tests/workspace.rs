@@ -0,0 +1,216 @@
+//! Tests for editing workspace manifests.
+
+use cargo_new_release::workspace::{self, Member, Workspace};
+use semver::Version;
+
+fn v(s: &str) -> Version {
+    Version::parse(s).unwrap()
+}
+
+#[test]
+fn version() {
+    let manifest = "[workspace]\n\
+                    members = [\"crates/*\"]\n\
+                    \n\
+                    [workspace.package]\n\
+                    version = \"0.70.0\"\n\
+                    \n\
+                    [workspace.dependencies]\n\
+                    semver = { version = \"1.0.0\" }\n\
+                    \n\
+                    [package]\n\
+                    name = \"cargo\"\n\
+                    version.workspace = true\n";
+    assert_eq!(workspace::version(manifest).unwrap(), v("0.70.0"));
+    assert_eq!(
+        workspace::set_version(manifest, &v("0.71.0")).unwrap(),
+        manifest.replace("0.70.0", "0.71.0")
+    );
+
+    let manifest = "[lints]\n\
+                    version = \"1\"\n\
+                    \n\
+                    [package]\n\
+                    name = 'cargo'\n\
+                    version = '0.70.0'\n";
+    assert_eq!(workspace::version(manifest).unwrap(), v("0.70.0"));
+    assert_eq!(
+        workspace::set_version(manifest, &v("0.71.0")).unwrap(),
+        manifest.replace("0.70.0", "0.71.0")
+    );
+
+    let manifest = "[workspace]\n\
+                    members = [\"crates/*\"]\n\
+                    \n\
+                    [workspace.package]\n\
+                    version = \"0.70.0\"\n";
+    assert_eq!(workspace::version(manifest).unwrap(), v("0.70.0"));
+
+    let error = |manifest| workspace::version(manifest).unwrap_err().to_string();
+    assert_eq!(
+        error("[package]\nname = \"cargo\"\nversion.workspace = true\n"),
+        "manifest has no `workspace.package.version` string"
+    );
+    assert_eq!(
+        error("[package]\nname = \"cargo\"\nversion = 70\n"),
+        "`package.version` is not a string"
+    );
+    assert_eq!(
+        error("[package]\nversion = \"0.70\"\n"),
+        "invalid `package.version` `0.70`"
+    );
+}
+
+#[test]
+fn set_version() {
+    let manifest = "[package]\n\
+                    name = \"crates-io\"\n\
+                    version = \"0.36.0\"  # bumped on release\n\
+                    \n\
+                    [dependencies]\n\
+                    url = { version = \"2.0.0\" }\n";
+    assert_eq!(
+        workspace::set_version(manifest, &v("0.36.1")).unwrap(),
+        manifest.replace("0.36.0", "0.36.1")
+    );
+    assert_eq!(
+        workspace::set_version("[package]\nname = \"cargo\"\n", &v("1.0.0"))
+            .unwrap_err()
+            .to_string(),
+        "manifest has no `package.version`"
+    );
+}
+
+#[test]
+fn update_requirements() {
+    let manifest = "[workspace]\n\
+                    members = [\"crates/*\"]\n\
+                    \n\
+                    [workspace.dependencies]\n\
+                    cargo = { path = \"\", version = \"0.70.0\" }\n\
+                    crates-io = { path = \"crates/crates-io\", version = \"0.36.0\" }\n\
+                    url = \"2.0.0\"\n\
+                    \n\
+                    [dependencies]\n\
+                    io = { package = \"crates-io\", path = \"crates/crates-io\", version = \"=0.36.0\" }\n\
+                    \n\
+                    [target.'cfg(unix)'.dev-dependencies.crates-io]\n\
+                    path = \"crates/crates-io\"\n\
+                    version = \"~0.36\"\n\
+                    \n\
+                    [build-dependencies]\n\
+                    crates-io = \"0.36.0\"\n";
+    let bumped = [
+        ("cargo".to_string(), v("0.71.0")),
+        ("crates-io".to_string(), v("0.36.1")),
+    ];
+    assert_eq!(
+        workspace::update_requirements(manifest, &bumped).unwrap(),
+        "[workspace]\n\
+         members = [\"crates/*\"]\n\
+         \n\
+         [workspace.dependencies]\n\
+         cargo = { path = \"\", version = \"0.71.0\" }\n\
+         crates-io = { path = \"crates/crates-io\", version = \"0.36.1\" }\n\
+         url = \"2.0.0\"\n\
+         \n\
+         [dependencies]\n\
+         io = { package = \"crates-io\", path = \"crates/crates-io\", version = \"=0.36.1\" }\n\
+         \n\
+         [target.'cfg(unix)'.dev-dependencies.crates-io]\n\
+         path = \"crates/crates-io\"\n\
+         version = \"~0.36.1\"\n\
+         \n\
+         [build-dependencies]\n\
+         crates-io = \"0.36.0\"\n"
+    );
+}
+
+#[test]
+fn update_lockfile() {
+    let lock = "# This file is automatically @generated by Cargo.\n\
+                version = 3\n\
+                \n\
+                [[package]]\n\
+                name = \"cargo\"\n\
+                version = \"0.70.0\"\n\
+                dependencies = [\n \"crates-io 0.36.0\",\n \"semver\",\n]\n\
+                \n\
+                [[package]]\n\
+                name = \"crates-io\"\n\
+                version = \"0.36.0\"\n\
+                \n\
+                [[package]]\n\
+                name = \"crates-io\"\n\
+                version = \"0.36.0\"\n\
+                source = \"registry+https://github.com/rust-lang/crates.io-index\"\n\
+                \n\
+                [[package]]\n\
+                name = \"semver\"\n\
+                version = \"1.0.0\"\n\
+                source = \"registry+https://github.com/rust-lang/crates.io-index\"\n";
+    let bumped = [
+        ("cargo".to_string(), v("0.71.0")),
+        ("crates-io".to_string(), v("0.36.1")),
+    ];
+    assert_eq!(
+        workspace::update_lockfile(lock, &bumped).unwrap(),
+        "# This file is automatically @generated by Cargo.\n\
+         version = 3\n\
+         \n\
+         [[package]]\n\
+         name = \"cargo\"\n\
+         version = \"0.71.0\"\n\
+         dependencies = [\n \"crates-io 0.36.1\",\n \"semver\",\n]\n\
+         \n\
+         [[package]]\n\
+         name = \"crates-io\"\n\
+         version = \"0.36.1\"\n\
+         \n\
+         [[package]]\n\
+         name = \"crates-io\"\n\
+         version = \"0.36.0\"\n\
+         source = \"registry+https://github.com/rust-lang/crates.io-index\"\n\
+         \n\
+         [[package]]\n\
+         name = \"semver\"\n\
+         version = \"1.0.0\"\n\
+         source = \"registry+https://github.com/rust-lang/crates.io-index\"\n"
+    );
+}
+
+#[test]
+fn lockfile_drift() {
+    let member = |name: &str, version: &str, dependencies: &[&str]| Member {
+        name: name.to_string(),
+        dir: format!("crates/{}", name),
+        version: Some(v(version)),
+        inherits_version: false,
+        publish: true,
+        dependencies: dependencies.iter().map(|d| d.to_string()).collect(),
+    };
+    let workspace = Workspace {
+        members: vec![
+            member("crates-io", "0.36.0", &["serde", "url"]),
+            member("cargo-util", "0.2.3", &[]),
+        ],
+    };
+    let lock = "version = 3\n\
+                \n\
+                [[package]]\n\
+                name = \"crates-io\"\n\
+                version = \"0.36.0\"\n\
+                dependencies = [\n \"serde 1.0.0\",\n]\n\
+                \n\
+                [[package]]\n\
+                name = \"cargo-util\"\n\
+                version = \"0.2.3\"\n\
+                source = \"registry+https://github.com/rust-lang/crates.io-index\"\n";
+    assert_eq!(
+        workspace::lockfile_drift(lock, &workspace).unwrap(),
+        [
+            "`crates-io` depends on `url`, which is not locked",
+            "`cargo-util` is missing",
+        ]
+    );
+}